@@ -0,0 +1,548 @@
+use crate::context::{AgentStatus, ContextStore, SessionContext, session_key};
+use crate::tmux::{TmuxClient, TmuxSession};
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Everything needed to start a session and describe it in the store.
+#[derive(Clone, Debug, Default)]
+pub struct NewSession {
+    pub name: String,
+    pub cwd: Option<PathBuf>,
+    pub command: Option<String>,
+    pub status: Option<AgentStatus>,
+    pub context: Option<String>,
+}
+
+/// Creates a detached tmux session and records its metadata under the id
+/// tmux assigned. The session is killed again if the store cannot be written,
+/// so neither side is left without the other. Mirroring the metadata into
+/// tmux options is best effort.
+pub fn create_session(
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+    request: &NewSession,
+) -> Result<TmuxSession, Box<dyn Error>> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err("Session name must not be empty".into());
+    }
+    let session = tmux.new_session(name, request.cwd.as_deref(), request.command.as_deref())?;
+    let entry = SessionContext {
+        session_name: Some(session.name.clone()),
+        session_id: Some(session.id.clone()),
+        server: tmux.socket_path().ok(),
+        status: request.status.clone(),
+        context: request.context.clone(),
+        ..SessionContext::default()
+    };
+    if let Err(error) = store.insert_session(entry) {
+        let _ = tmux.kill_session(&session.id);
+        return Err(error);
+    }
+    let _ = crate::mirror::session(tmux, store, &session.name);
+    Ok(session)
+}
+
+/// Renames a session in tmux and moves its metadata to the new name's key.
+/// tmux gets the old name back if the store cannot be updated. tmux replaces
+/// `.` and `:` in names, so the store follows the name tmux reports.
+pub fn rename_session(
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+    session_id: &str,
+    old_name: &str,
+    new_name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err("Session name must not be empty".into());
+    }
+    if new_name == old_name {
+        return Ok(());
+    }
+    tmux.rename_session(session_id, new_name)?;
+    let new_name = tmux
+        .snapshot()
+        .ok()
+        .and_then(|snapshot| snapshot.session(session_id).map(|s| s.session.name.clone()))
+        .unwrap_or_else(|| new_name.to_string());
+    if let Err(error) = store.rename_session(session_id, &new_name) {
+        let _ = tmux.rename_session(session_id, old_name);
+        return Err(error);
+    }
+    // Renaming may merge entries, so the options follow the stored values.
+    let _ = crate::mirror::session(tmux, store, &new_name);
+    Ok(())
+}
+
+/// A session or pane to close, identified for both tmux and the store.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KillTarget {
+    Session {
+        session_id: String,
+        session_name: String,
+    },
+    Pane {
+        session_id: String,
+        session_name: String,
+        pane_id: String,
+    },
+}
+
+/// Kills each target in tmux and drops its metadata: killed sessions are
+/// archived, killed panes removed. A pane that was the last one in its
+/// session takes the session with it, so that session is archived too.
+pub fn kill(
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+    targets: &[KillTarget],
+) -> Result<(), Box<dyn Error>> {
+    for target in targets {
+        match target {
+            KillTarget::Session {
+                session_id,
+                session_name,
+            } => {
+                tmux.kill_session(session_id)?;
+                store.archive_session(session_name)?;
+            }
+            KillTarget::Pane {
+                session_id,
+                session_name,
+                pane_id,
+            } => {
+                tmux.kill_pane(pane_id)?;
+                store.remove_pane(session_name, pane_id)?;
+                let session_alive = match tmux.snapshot() {
+                    Ok(snapshot) => snapshot.session(session_id).is_some(),
+                    // The last pane of the last session takes the server down.
+                    Err(error) if error.is_unavailable() => false,
+                    Err(error) => return Err(error.into()),
+                };
+                if !session_alive {
+                    store.archive_session(session_name)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What `collect_garbage` changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GarbageReport {
+    pub archived_sessions: usize,
+    pub pruned_panes: usize,
+    pub finished_panes: usize,
+}
+
+/// Which store entries `collect_garbage` looks at. The store is shared by
+/// every tmux server, so the hooks narrow it down to what just happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GarbageScope<'a> {
+    /// Every entry.
+    All,
+    /// One session's entry, as `session-closed` passes it.
+    Session(&'a str),
+    /// The entry of the session a pane belonged to, as `pane-exited` and
+    /// `pane-died` pass it.
+    Pane(&'a str),
+}
+
+/// Brings the store in line with tmux after sessions close or panes exit:
+/// entries of closed sessions are archived, entries of closed panes removed,
+/// and dead panes kept by `remain-on-exit` get `done` or `failed` from their
+/// exit status unless they already report one of those.
+///
+/// Live sessions are recorded as this server's. A closed session is only
+/// archived when it was, or, with no owner recorded yet, when a hook named
+/// it; sessions of other servers sharing the store are left to them.
+pub fn collect_garbage(
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+    scope: GarbageScope,
+) -> Result<GarbageReport, Box<dyn Error>> {
+    let snapshot = tmux.snapshot()?;
+    let server = tmux.socket_path()?;
+    let live_sessions = snapshot
+        .sessions()
+        .map(|session| session.name.as_str())
+        .collect::<HashSet<_>>();
+    let contexts = store.load()?;
+    let sessions = match scope {
+        GarbageScope::All => None,
+        GarbageScope::Session(session_name) => Some(HashSet::from([session_name.to_string()])),
+        // A dead pane is still there to ask; an exited one is looked up in
+        // the entries of this server's sessions.
+        GarbageScope::Pane(pane_id) => Some(match tmux.describe_pane(pane_id) {
+            Ok(pane) => HashSet::from([pane.session_name]),
+            Err(_) => contexts
+                .values()
+                .filter(|entry| entry.panes.contains_key(pane_id))
+                .filter_map(|entry| entry.session_name.clone())
+                .filter(|session_name| live_sessions.contains(session_name.as_str()))
+                .collect(),
+        }),
+    };
+    let in_scope = |session_name: &str| {
+        sessions
+            .as_ref()
+            .is_none_or(|sessions| sessions.contains(session_name))
+    };
+    let mut live_panes = snapshot.live_panes();
+    live_panes.retain(|session_name, _| in_scope(session_name));
+
+    let unclaimed = contexts
+        .values()
+        .filter(|entry| entry.server.as_deref() != Some(server.as_str()))
+        .filter_map(|entry| entry.session_name.clone())
+        .filter(|session_name| {
+            live_sessions.contains(session_name.as_str()) && in_scope(session_name)
+        })
+        .collect::<Vec<_>>();
+    if !unclaimed.is_empty() {
+        store.set_server(&unclaimed, &server)?;
+    }
+
+    let mut report = GarbageReport::default();
+    for entry in contexts.into_values() {
+        let Some(session_name) = entry.session_name else {
+            continue;
+        };
+        if !in_scope(&session_name) {
+            continue;
+        }
+        if !live_sessions.contains(session_name.as_str()) {
+            let ours = match entry.server.as_deref() {
+                Some(owner) => owner == server,
+                None => scope != GarbageScope::All,
+            };
+            if ours {
+                store.archive_session(&session_name)?;
+                report.archived_sessions += 1;
+            }
+            continue;
+        }
+        if let Some(live) = live_panes.get(&session_name) {
+            report.pruned_panes += entry
+                .panes
+                .keys()
+                .filter(|pane_id| !live.contains(*pane_id))
+                .count();
+        }
+    }
+    store.prune_panes(&live_panes)?;
+
+    let contexts = store.load()?;
+    let now = crate::context::unix_now();
+    for pane in snapshot
+        .panes()
+        .filter(|pane| pane.dead && in_scope(&pane.session_name))
+    {
+        let Some(stored) = contexts
+            .get(&session_key(&pane.session_name))
+            .and_then(|entry| entry.panes.get(&pane.pane_id))
+        else {
+            continue;
+        };
+        if matches!(stored.status, Some(AgentStatus::Done | AgentStatus::Failed)) {
+            continue;
+        }
+        let status = crate::infer::pane_status(pane, now);
+        store.upsert_pane(&pane.session_name, &pane.pane_id, status, None)?;
+        let _ = crate::mirror::pane(tmux, store, &pane.session_name, &pane.pane_id);
+        report.finished_panes += 1;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tmux::fake::FakeTmux;
+
+    #[test]
+    fn create_session_stores_metadata_under_the_new_id() {
+        let tmux = FakeTmux::new();
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "agent".to_string(),
+                Some("$7".to_string()),
+                None,
+                Some("old".to_string()),
+            )
+            .unwrap();
+
+        let session = create_session(
+            &tmux,
+            &store,
+            &NewSession {
+                name: "agent".to_string(),
+                status: Some(AgentStatus::Working),
+                ..NewSession::default()
+            },
+        )
+        .unwrap();
+
+        let contexts = store.load().unwrap();
+        let entry = &contexts[&session_key("agent")];
+        assert_eq!(entry.session_id.as_deref(), Some(session.id.as_str()));
+        assert_eq!(entry.status, Some(AgentStatus::Working));
+        assert_eq!(entry.context, None);
+        assert!(create_session(&tmux, &store, &NewSession::default()).is_err());
+        assert!(
+            create_session(
+                &tmux,
+                &store,
+                &NewSession {
+                    name: "agent".to_string(),
+                    ..NewSession::default()
+                }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn rename_moves_tmux_session_and_metadata_together() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.add_session("$2", "web");
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "api".to_string(),
+                Some("$1".to_string()),
+                Some(AgentStatus::Waiting),
+                None,
+            )
+            .unwrap();
+
+        rename_session(&tmux, &store, "$1", "api", "api v2").unwrap();
+        assert!(rename_session(&tmux, &store, "$1", "api v2", "web").is_err());
+        assert!(rename_session(&tmux, &store, "$1", "api v2", " ").is_err());
+
+        assert_eq!(tmux.calls(), vec!["rename-session $1"]);
+        assert_eq!(tmux.snapshot().unwrap().sessions[0].session.name, "api v2");
+        let contexts = store.load().unwrap();
+        assert!(!contexts.contains_key(&session_key("api")));
+        assert_eq!(
+            contexts[&session_key("api v2")].status,
+            Some(AgentStatus::Waiting)
+        );
+    }
+
+    #[test]
+    fn rename_follows_the_name_tmux_settles_on() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "api".to_string(),
+                Some("$1".to_string()),
+                Some(AgentStatus::Waiting),
+                None,
+            )
+            .unwrap();
+
+        rename_session(&tmux, &store, "$1", "api", "api.v2:beta").unwrap();
+
+        assert_eq!(
+            tmux.snapshot().unwrap().sessions[0].session.name,
+            "api_v2_beta"
+        );
+        let contexts = store.load().unwrap();
+        assert!(!contexts.contains_key(&session_key("api.v2:beta")));
+        assert_eq!(
+            contexts[&session_key("api_v2_beta")].status,
+            Some(AgentStatus::Waiting)
+        );
+    }
+
+    #[test]
+    fn garbage_collection_archives_prunes_and_finishes() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.add_pane("api", "%1");
+        tmux.push_pane(crate::tmux::TmuxPane {
+            session_name: "api".to_string(),
+            pane_id: "%2".to_string(),
+            dead: true,
+            dead_status: Some(1),
+            ..crate::tmux::TmuxPane::default()
+        });
+        let store = ContextStore::temp();
+        for pane_id in ["%1", "%2", "%3"] {
+            store
+                .upsert_pane("api", pane_id, Some(AgentStatus::Working), None)
+                .unwrap();
+        }
+        // Only a closed session this server owned is archived; one another
+        // server owns, or no server has claimed yet, may still be alive.
+        for (session_name, server) in [
+            ("gone", Some("/tmp/tmux-0/default")),
+            ("elsewhere", Some("/tmp/tmux-0/work")),
+            ("unclaimed", None),
+        ] {
+            store
+                .insert_session(SessionContext {
+                    session_name: Some(session_name.to_string()),
+                    server: server.map(str::to_string),
+                    ..SessionContext::default()
+                })
+                .unwrap();
+        }
+
+        let report = collect_garbage(&tmux, &store, GarbageScope::All).unwrap();
+
+        assert_eq!(
+            report,
+            GarbageReport {
+                archived_sessions: 1,
+                pruned_panes: 1,
+                finished_panes: 1,
+            }
+        );
+        let contexts = store.load().unwrap();
+        let api = &contexts[&session_key("api")];
+        assert_eq!(api.panes.len(), 2);
+        assert_eq!(api.panes["%1"].status, Some(AgentStatus::Working));
+        assert_eq!(api.panes["%2"].status, Some(AgentStatus::Failed));
+        assert_eq!(api.server.as_deref(), Some("/tmp/tmux-0/default"));
+        assert!(!contexts.contains_key(&session_key("gone")));
+        assert!(contexts.contains_key(&session_key("elsewhere")));
+        assert!(contexts.contains_key(&session_key("unclaimed")));
+        assert_eq!(
+            collect_garbage(&tmux, &store, GarbageScope::All).unwrap(),
+            GarbageReport::default()
+        );
+    }
+
+    #[test]
+    fn garbage_collection_for_a_session_leaves_other_servers_alone() {
+        let work = FakeTmux::on_socket("/tmp/tmux-0/work");
+        work.add_session("$0", "api");
+        work.add_pane("api", "%0");
+        let default = FakeTmux::on_socket("/tmp/tmux-0/default");
+        default.add_session("$0", "web");
+        default.add_pane("web", "%0");
+        let store = ContextStore::temp();
+        for session_name in ["api", "web", "closed"] {
+            store
+                .upsert_pane(session_name, "%0", Some(AgentStatus::Working), None)
+                .unwrap();
+        }
+
+        let report = collect_garbage(&work, &store, GarbageScope::Session("closed")).unwrap();
+
+        assert_eq!(report.archived_sessions, 1);
+        let contexts = store.load().unwrap();
+        assert!(contexts.contains_key(&session_key("api")));
+        assert_eq!(contexts[&session_key("web")].panes.len(), 1);
+        assert!(!contexts.contains_key(&session_key("closed")));
+        assert_eq!(
+            collect_garbage(&default, &store, GarbageScope::Session("web")).unwrap(),
+            GarbageReport::default()
+        );
+        assert!(store.load().unwrap().contains_key(&session_key("api")));
+
+        collect_garbage(&work, &store, GarbageScope::All).unwrap();
+        assert_eq!(
+            collect_garbage(&default, &store, GarbageScope::All).unwrap(),
+            GarbageReport::default()
+        );
+        let contexts = store.load().unwrap();
+        assert_eq!(
+            contexts[&session_key("api")].server.as_deref(),
+            Some("/tmp/tmux-0/work")
+        );
+        assert_eq!(
+            contexts[&session_key("web")].server.as_deref(),
+            Some("/tmp/tmux-0/default")
+        );
+    }
+
+    #[test]
+    fn killing_the_only_pane_archives_its_session() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$0", "api");
+        tmux.add_pane("api", "%0");
+        let store = ContextStore::temp();
+        store
+            .upsert_pane("api", "%0", Some(AgentStatus::Working), None)
+            .unwrap();
+
+        kill(
+            &tmux,
+            &store,
+            &[KillTarget::Pane {
+                session_id: "$0".to_string(),
+                session_name: "api".to_string(),
+                pane_id: "%0".to_string(),
+            }],
+        )
+        .unwrap();
+
+        assert!(tmux.snapshot().unwrap_err().is_unavailable());
+        assert!(store.load().unwrap().is_empty());
+        assert_eq!(store.archived().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn kill_archives_sessions_and_removes_panes() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.add_pane("api", "%1");
+        tmux.add_pane("api", "%2");
+        tmux.add_session("$2", "web");
+        tmux.add_pane("web", "%3");
+        let store = ContextStore::temp();
+        store
+            .upsert_session("api".to_string(), Some("$1".to_string()), None, None)
+            .unwrap();
+        store
+            .upsert_pane("api", "%1", Some(AgentStatus::Done), None)
+            .unwrap();
+        store
+            .upsert_pane("web", "%3", Some(AgentStatus::Failed), None)
+            .unwrap();
+
+        let pane = |session_id: &str, session_name: &str, pane_id: &str| KillTarget::Pane {
+            session_id: session_id.to_string(),
+            session_name: session_name.to_string(),
+            pane_id: pane_id.to_string(),
+        };
+        kill(
+            &tmux,
+            &store,
+            &[pane("$1", "api", "%1"), pane("$2", "web", "%3")],
+        )
+        .unwrap();
+
+        let contexts = store.load().unwrap();
+        assert!(contexts[&session_key("api")].panes.is_empty());
+        assert!(!contexts.contains_key(&session_key("web")));
+        assert_eq!(store.archived().unwrap().len(), 1);
+
+        kill(
+            &tmux,
+            &store,
+            &[KillTarget::Session {
+                session_id: "$1".to_string(),
+                session_name: "api".to_string(),
+            }],
+        )
+        .unwrap();
+        assert!(store.load().unwrap().is_empty());
+        let archived = store.archived().unwrap();
+        assert_eq!(archived[1].session.session_id.as_deref(), Some("$1"));
+        assert_eq!(
+            tmux.calls(),
+            vec!["kill-pane %1", "kill-pane %3", "kill-session $1"]
+        );
+        assert!(tmux.snapshot().unwrap().sessions.is_empty());
+    }
+}
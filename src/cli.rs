@@ -1,16 +1,54 @@
+use crate::actions::NewSession;
+use crate::context::ContextStore;
+use crate::hooks::HookState;
+use crate::list::ListOptions;
+use crate::status_line::StatusLineOptions;
+use crate::tmux::{Handoff, SystemTmux, Target, TmuxClient, TmuxError, TmuxSocket};
 use clap::{Args, Parser, Subcommand};
 use std::io;
+use std::path::PathBuf;
+
+const SOCKET_ENV: &str = "JKL_TMUX_SOCKET";
+const SOCKETS_ENV: &str = "JKL_TMUX_SOCKETS";
 
 pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    let tmux = SystemTmux::new(cli.tmux_socket());
+    let store = ContextStore::from_env();
     match cli.command {
-        Commands::Tui(args) => handle_tui(args),
-        Commands::Upsert(args) => handle_upsert(args),
-        Commands::Rename(args) => handle_rename(args),
+        Commands::Tui(args) => handle_tui(args, &tmux, store),
+        Commands::New(args) => handle_new(args, &tmux, &store),
+        Commands::Upsert(args) => handle_upsert(args, &tmux, &store),
+        Commands::Rename(args) => handle_rename(args, &tmux, &store),
+        Commands::Gc(args) => {
+            let report = handle_gc(args, &tmux, &store)?;
+            println!(
+                "Archived {} sessions, pruned {} panes, finished {} panes",
+                report.archived_sessions, report.pruned_panes, report.finished_panes
+            );
+            Ok(())
+        }
+        Commands::Seen(args) => handle_seen(args, &store),
+        Commands::Hooks(args) => handle_hooks(args, &tmux),
+        Commands::StatusLine(args) => handle_status_line(args, &tmux, &store),
+        Commands::List(args) => handle_list(args, &tmux, &store),
+        Commands::SyncTmux => {
+            let count = crate::mirror::sync(&tmux, &store)?;
+            println!("Updated {count} sessions and panes");
+            Ok(())
+        }
+        Commands::Switch(args) => {
+            let handoff = Handoff::detect(args.client.clone());
+            handle_switch(args, &tmux, &handoff)
+        }
     }
 }
 
-fn handle_tui(args: TuiArgs) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_tui(
+    args: TuiArgs,
+    tmux: &dyn TmuxClient,
+    store: ContextStore,
+) -> Result<(), Box<dyn std::error::Error>> {
     if args.pane_state {
         let session_name = args
             .session_name
@@ -19,51 +57,342 @@ fn handle_tui(args: TuiArgs) -> Result<(), Box<dyn std::error::Error>> {
         let pane_id = args
             .pane_id
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Missing --pane-id"))?;
-        return crate::tui::run_pane_selector(session_name, pane_id);
+        return crate::tui::run_pane_selector(tmux, store, session_name, pane_id);
     }
-    crate::tui::run()
+    let options = crate::tui::TuiOptions {
+        infer: args.infer,
+        client: args.client,
+        servers: tui_servers(args.all_servers),
+        search: if args.fzf {
+            crate::tui::SearchBackend::Fzf
+        } else {
+            crate::tui::SearchBackend::Builtin
+        },
+    };
+    crate::tui::run(tmux, store, options)
 }
 
-fn handle_upsert(args: UpsertArgs) -> Result<(), Box<dyn std::error::Error>> {
+/// The servers listed by the TUI: those in `JKL_TMUX_SOCKETS`, or with
+/// `--all-servers` every socket in the tmux socket directory.
+fn tui_servers(all_servers: bool) -> Vec<TmuxSocket> {
+    if let Ok(list) = std::env::var(SOCKETS_ENV) {
+        let sockets = TmuxSocket::from_env_list(&list);
+        if !sockets.is_empty() {
+            return sockets;
+        }
+    }
+    if all_servers {
+        return crate::tmux::discover_sockets(&crate::tmux::socket_dir());
+    }
+    Vec::new()
+}
+
+fn handle_new(
+    args: NewArgs,
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let status = match args.status {
+        Some(status) => Some(status.parse()?),
+        None => None,
+    };
+    let request = NewSession {
+        name: join_tokens(args.session_name),
+        cwd: args.cwd,
+        command: args.command.map(join_tokens),
+        status,
+        context: args.context.map(join_tokens),
+    };
+    let session = crate::actions::create_session(tmux, store, &request)?;
+    println!("{}", session.id);
+    Ok(())
+}
+
+fn handle_upsert(
+    args: UpsertArgs,
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+) -> Result<(), Box<dyn std::error::Error>> {
     let status = match args.status {
         Some(status) => Some(status.parse()?),
         None => None,
     };
-    let session_name = join_tokens(args.session_name);
     let context = args.context.map(join_tokens);
+    if !args.session_name.is_empty() {
+        let session_name = join_tokens(args.session_name);
+        if let Some(pane_id) = args.pane_id {
+            store.upsert_pane(&session_name, &pane_id, status, context)?;
+            warn_on_mirror_error(crate::mirror::pane(tmux, store, &session_name, &pane_id));
+            return Ok(());
+        }
+        store.upsert_session(session_name.clone(), args.session_id, status, context)?;
+        warn_on_mirror_error(crate::mirror::session(tmux, store, &session_name));
+        return Ok(());
+    }
+
+    // No session name: resolve it from the pane, defaulting to the calling one.
+    let pane_id = match args.pane_id {
+        Some(pane_id) => pane_id,
+        None => current_pane_id()?,
+    };
+    let pane = tmux.describe_pane(&pane_id)?;
+    if args.session {
+        let session_id = args.session_id.unwrap_or(pane.session_id);
+        store.upsert_session(pane.session_name.clone(), Some(session_id), status, context)?;
+        warn_on_mirror_error(crate::mirror::session(tmux, store, &pane.session_name));
+        return Ok(());
+    }
+    store.upsert_pane(&pane.session_name, &pane.pane_id, status, context)?;
+    warn_on_mirror_error(crate::mirror::pane(
+        tmux,
+        store,
+        &pane.session_name,
+        &pane.pane_id,
+    ));
+    Ok(())
+}
+
+/// The store is the source of truth, so a tmux server that cannot take the
+/// options (not running, target gone) does not fail the command.
+fn warn_on_mirror_error(result: Result<(), Box<dyn std::error::Error>>) {
+    if let Err(error) = result {
+        eprintln!("jkl: could not update tmux options: {error}");
+    }
+}
+
+fn current_pane_id() -> Result<String, io::Error> {
+    std::env::var("TMUX_PANE")
+        .ok()
+        .filter(|pane_id| !pane_id.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Not running inside tmux: pass a session name or --pane-id",
+            )
+        })
+}
+
+fn handle_list(
+    args: ListArgs,
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = ListOptions {
+        query: query_from_args(args.query),
+        panes: args.panes,
+    };
+    let snapshot = tmux.snapshot()?;
+    print!("{}", crate::list::render(store, &snapshot, &options)?);
+    Ok(())
+}
+
+fn handle_status_line(
+    args: StatusLineArgs,
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = StatusLineOptions {
+        format: args.format,
+        session_name: args.session.map(join_tokens),
+        color: !args.no_color,
+    };
+    // Without a server there is nothing to check liveness against.
+    let live = tmux.snapshot().ok().map(|snapshot| snapshot.live_panes());
+    println!(
+        "{}",
+        crate::status_line::render(store, live.as_ref(), &options)?
+    );
+    Ok(())
+}
+
+fn handle_hooks(args: HooksArgs, tmux: &dyn TmuxClient) -> Result<(), Box<dyn std::error::Error>> {
+    // Hooks outlive this process, so they must not depend on $PATH.
+    let exe = std::env::current_exe()?;
+    let statuses = match args.action {
+        HooksAction::Install => crate::hooks::install(tmux, &exe)?,
+        HooksAction::Uninstall => {
+            let removed = crate::hooks::uninstall(tmux)?;
+            println!("Removed {removed} hook commands");
+            return Ok(());
+        }
+        HooksAction::Status => crate::hooks::status(tmux, &exe)?,
+    };
+    for status in statuses {
+        match status.state {
+            HookState::Installed => println!("{}: installed", status.name),
+            HookState::Missing => println!("{}: missing", status.name),
+            HookState::Drifted(entries) => {
+                println!("{}: drifted, expected {}", status.name, status.expected);
+                for entry in entries {
+                    println!("  [{}] {}", entry.index, entry.command);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_rename(
+    args: RenameArgs,
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let session_name = join_tokens(args.session_name);
+    store.rename_session(&args.session_id, &session_name)?;
+    warn_on_mirror_error(crate::mirror::session(tmux, store, &session_name));
+    Ok(())
+}
+
+fn handle_switch(
+    args: SwitchArgs,
+    tmux: &dyn TmuxClient,
+    handoff: &Handoff,
+) -> Result<(), Box<dyn std::error::Error>> {
     if let Some(pane_id) = args.pane_id {
-        return crate::context::upsert_pane(&session_name, &pane_id, status, context);
+        let snapshot = tmux.snapshot()?;
+        let pane = snapshot
+            .panes()
+            .find(|pane| pane.pane_id == pane_id)
+            .ok_or_else(|| TmuxError::TargetNotFound(format!("Unknown pane: {pane_id}")))?;
+        let target = Target::pane(&pane.session_id, &pane.window_id, &pane.pane_id);
+        tmux.open(handoff, &target)?;
+        return Ok(());
+    }
+    if let Some(session_id) = args.session_id {
+        tmux.open(handoff, &Target::session(&session_id))?;
+        return Ok(());
     }
-    crate::context::upsert_session(session_name, args.session_id, status, context)?;
+    if args.session_name.is_empty() {
+        return Err(Box::new(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Missing session name, --session-id or --pane-id",
+        )));
+    }
+    let session_name = join_tokens(args.session_name);
+    let snapshot = tmux.snapshot()?;
+    let session = snapshot
+        .sessions()
+        .find(|session| session.name == session_name)
+        .ok_or_else(|| TmuxError::TargetNotFound(format!("Unknown session: {session_name}")))?;
+    tmux.open(handoff, &Target::session(&session.id))?;
     Ok(())
 }
 
-fn handle_rename(args: RenameArgs) -> Result<(), Box<dyn std::error::Error>> {
-    crate::context::rename_session(&args.session_id, &join_tokens(args.session_name))
+/// An empty `--session` or `--pane` means tmux expanded a format to nothing;
+/// collecting every entry instead would reach into other servers' sessions.
+fn handle_gc(
+    args: GcArgs,
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+) -> Result<crate::actions::GarbageReport, Box<dyn std::error::Error>> {
+    use crate::actions::GarbageScope;
+    let scope = match (args.session.as_deref(), args.pane.as_deref()) {
+        (Some(session), _) if session.trim().is_empty() => {
+            return Err("Empty --session; refusing to collect every entry".into());
+        }
+        (_, Some(pane)) if pane.trim().is_empty() => {
+            return Err("Empty --pane; refusing to collect every entry".into());
+        }
+        (Some(session), _) => GarbageScope::Session(session),
+        (_, Some(pane)) => GarbageScope::Pane(pane),
+        (None, None) => GarbageScope::All,
+    };
+    crate::actions::collect_garbage(tmux, store, scope)
+}
+
+fn handle_seen(args: SeenArgs, store: &ContextStore) -> Result<(), Box<dyn std::error::Error>> {
+    if args.control_mode {
+        return Ok(());
+    }
+    store.mark_seen(&join_tokens(args.session_name))
 }
 
 fn join_tokens(tokens: Vec<String>) -> String {
     tokens.join(" ")
 }
 
+/// Joins search words, quoting those the shell already grouped, so
+/// `jkl list ctx:"flaky test"` filters on the whole phrase.
+fn query_from_args(args: Vec<String>) -> String {
+    args.into_iter()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) && !arg.contains('"') {
+                format!("\"{arg}\"")
+            } else {
+                arg
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[derive(Parser)]
 #[command(name = "jkl", version)]
 struct Cli {
+    /// tmux server socket name, as in `tmux -L` (overrides JKL_TMUX_SOCKET)
+    #[arg(long, global = true, conflicts_with = "socket_path")]
+    socket_name: Option<String>,
+    /// tmux server socket path, as in `tmux -S` (overrides JKL_TMUX_SOCKET)
+    #[arg(long, global = true)]
+    socket_path: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Cli {
+    fn tmux_socket(&self) -> TmuxSocket {
+        if let Some(name) = &self.socket_name {
+            return TmuxSocket::Name(name.clone());
+        }
+        if let Some(path) = &self.socket_path {
+            return TmuxSocket::Path(path.clone());
+        }
+        std::env::var(SOCKET_ENV)
+            .map(|value| TmuxSocket::from_env_value(&value))
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     Tui(TuiArgs),
+    /// Create a detached tmux session and record its metadata
+    New(NewArgs),
     Upsert(UpsertArgs),
     Rename(RenameArgs),
+    /// Archive entries of closed sessions, prune closed panes and finish dead ones
+    Gc(GcArgs),
+    /// Record that a client switched to a session
+    Seen(SeenArgs),
+    /// Manage the tmux hooks that keep metadata in sync
+    Hooks(HooksArgs),
+    /// Print agent counts per status for the tmux status bar
+    StatusLine(StatusLineArgs),
+    /// List sessions and panes matching a search, as in the TUI
+    List(ListArgs),
+    /// Copy every stored status and context into @jkl_status/@jkl_context options
+    SyncTmux,
+    Switch(SwitchArgs),
 }
 
 #[derive(Args)]
 struct TuiArgs {
     #[arg(long)]
     pane_state: bool,
+    /// Suggest statuses from tmux bells, activity and dead panes
+    #[arg(long)]
+    infer: bool,
+    /// List sessions of every tmux server with a socket in the tmux socket
+    /// directory (see also JKL_TMUX_SOCKETS)
+    #[arg(long)]
+    all_servers: bool,
+    /// Filter searches with an external `fzf --filter` instead of the
+    /// built-in matcher
+    #[arg(long)]
+    fzf: bool,
+    /// Client to switch when inside tmux (see `tmux list-clients`)
+    #[arg(long)]
+    client: Option<String>,
     #[arg(long, num_args = 1..)]
     session_name: Option<Vec<String>>,
     #[arg(long)]
@@ -71,9 +400,29 @@ struct TuiArgs {
 }
 
 #[derive(Args)]
-struct UpsertArgs {
+struct NewArgs {
     #[arg(num_args = 1..)]
     session_name: Vec<String>,
+    /// Working directory for the session's first pane
+    #[arg(long)]
+    cwd: Option<PathBuf>,
+    /// Command to run instead of the default shell
+    #[arg(long, num_args = 1..)]
+    command: Option<Vec<String>>,
+    #[arg(long)]
+    status: Option<String>,
+    #[arg(long, num_args = 1..)]
+    context: Option<Vec<String>>,
+}
+
+#[derive(Args)]
+struct UpsertArgs {
+    /// Defaults to the session of --pane-id or of the calling pane ($TMUX_PANE)
+    #[arg(num_args = 0..)]
+    session_name: Vec<String>,
+    /// Update the resolved pane's session instead of the pane itself
+    #[arg(long, conflicts_with = "session_name")]
+    session: bool,
     #[arg(long)]
     session_id: Option<String>,
     #[arg(long)]
@@ -84,9 +433,298 @@ struct UpsertArgs {
     context: Option<Vec<String>>,
 }
 
+#[derive(Args)]
+struct GcArgs {
+    /// Only collect this session's entry, as the session-closed hook does
+    #[arg(long, conflicts_with = "pane")]
+    session: Option<String>,
+    /// Only collect the entry of this pane's session, as the pane hooks do
+    #[arg(long)]
+    pane: Option<String>,
+}
+
+#[derive(Args)]
+struct SeenArgs {
+    /// `#{client_control_mode}` of the switching client. Control clients,
+    /// such as the one the TUI listens on, do not count as seeing a session.
+    #[arg(
+        long,
+        default_value_t = false,
+        action = clap::ArgAction::Set,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    control_mode: bool,
+    #[arg(num_args = 1..)]
+    session_name: Vec<String>,
+}
+
+#[derive(Args)]
+struct HooksArgs {
+    #[command(subcommand)]
+    action: HooksAction,
+}
+
+#[derive(Subcommand)]
+enum HooksAction {
+    /// Register the hooks with this binary's path, replacing drifted ones
+    Install,
+    /// Remove every jkl hook command
+    Uninstall,
+    /// Show which hooks are installed, missing or drifted
+    Status,
+}
+
+#[derive(Args)]
+struct StatusLineArgs {
+    /// Template with {summary}, {total}, {failed}, {waiting}, {working}, {idle} and {done}
+    #[arg(long, default_value = crate::status_line::DEFAULT_FORMAT)]
+    format: String,
+    /// Count a single session, e.g. '#{session_name}', instead of all of them
+    #[arg(long, num_args = 1..)]
+    session: Option<Vec<String>>,
+    /// Leave out #[fg=...] color markup
+    #[arg(long)]
+    no_color: bool,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Field filters (status:waiting, name:api, pane:%4, ctx:"flaky test",
+    /// updated:<10m) and fuzzy free text
+    #[arg(num_args = 0..)]
+    query: Vec<String>,
+    /// List every pane of a matching session
+    #[arg(long)]
+    panes: bool,
+}
+
 #[derive(Args)]
 struct RenameArgs {
     session_id: String,
     #[arg(num_args = 1..)]
     session_name: Vec<String>,
 }
+
+#[derive(Args)]
+struct SwitchArgs {
+    /// Client to switch when inside tmux (see `tmux list-clients`)
+    #[arg(long)]
+    client: Option<String>,
+    #[arg(num_args = 1..)]
+    session_name: Vec<String>,
+    #[arg(long)]
+    session_id: Option<String>,
+    #[arg(long)]
+    pane_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tmux::fake::FakeTmux;
+
+    fn upsert_args(args: &[&str]) -> UpsertArgs {
+        let cli = Cli::try_parse_from(["jkl", "upsert"].iter().chain(args)).unwrap();
+        match cli.command {
+            Commands::Upsert(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Runs a hook's jkl arguments the way tmux would: with its formats
+    /// expanded, a missing variable becoming empty.
+    fn run_hook(
+        hook: &str,
+        vars: &[(&str, &str)],
+        tmux: &FakeTmux,
+        store: &ContextStore,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, args) = crate::hooks::HOOKS
+            .iter()
+            .find(|(name, _)| *name == hook)
+            .unwrap();
+        let mut args = args.to_string();
+        while let Some(start) = args.find("#{") {
+            let end = start + args[start..].find('}').unwrap();
+            let name = args[start + 2..end].trim_start_matches("q:");
+            let value = vars
+                .iter()
+                .find(|(var, _)| *var == name)
+                .map_or("", |(_, value)| value);
+            args.replace_range(start..=end, value);
+        }
+        let cli = Cli::try_parse_from(std::iter::once("jkl").chain(args.split_whitespace()))?;
+        match cli.command {
+            Commands::Gc(args) => handle_gc(args, tmux, store).map(|_| ()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn gc_hooks_only_touch_the_session_they_fired_for() {
+        use crate::context::session_key;
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.add_pane("api", "%1");
+        tmux.add_session("$2", "keep");
+        tmux.add_pane("keep", "%3");
+        let store = ContextStore::temp();
+        for (session_name, pane_id) in [("api", "%1"), ("api", "%2"), ("clientB-work", "%1")] {
+            store
+                .upsert_pane(session_name, pane_id, None, Some("ctx".to_string()))
+                .unwrap();
+        }
+        store
+            .upsert_session("gone".to_string(), None, None, None)
+            .unwrap();
+
+        // An exited pane's `session_name` is some other session, and tmux
+        // never sets `hook_session_name` for pane hooks.
+        let exited = [("hook_pane", "%2"), ("session_name", "keep")];
+        run_hook("pane-exited", &exited, &tmux, &store).unwrap();
+        let contexts = store.load().unwrap();
+        assert!(!contexts[&session_key("api")].panes.contains_key("%2"));
+        assert!(contexts.contains_key(&session_key("clientB-work")));
+        assert!(contexts.contains_key(&session_key("gone")));
+
+        run_hook(
+            "session-closed",
+            &[("hook_session_name", "gone")],
+            &tmux,
+            &store,
+        )
+        .unwrap();
+        let contexts = store.load().unwrap();
+        assert!(!contexts.contains_key(&session_key("gone")));
+        assert!(contexts.contains_key(&session_key("clientB-work")));
+
+        assert!(run_hook("session-closed", &[], &tmux, &store).is_err());
+        assert!(run_hook("pane-died", &[], &tmux, &store).is_err());
+        assert!(
+            store
+                .load()
+                .unwrap()
+                .contains_key(&session_key("clientB-work"))
+        );
+    }
+
+    #[test]
+    fn seen_skips_control_clients_and_untracked_sessions() {
+        let store = ContextStore::temp();
+        store
+            .upsert_session("my work".to_string(), None, None, None)
+            .unwrap();
+        let seen = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["jkl", "seen"].iter().chain(args)).unwrap();
+            match cli.command {
+                Commands::Seen(args) => handle_seen(args, &store).unwrap(),
+                _ => unreachable!(),
+            }
+            store.load().unwrap()[&crate::context::session_key("my work")].seen_at
+        };
+
+        assert_eq!(seen(&["--control-mode", "1", "my", "work"]), None);
+        assert!(seen(&["--control-mode", "0", "my", "work"]).is_some());
+
+        // Sessions jkl does not track get no entry.
+        let cli = Cli::try_parse_from(["jkl", "seen", "scratch"]).unwrap();
+        let Commands::Seen(args) = cli.command else {
+            unreachable!()
+        };
+        handle_seen(args, &store).unwrap();
+        assert_eq!(store.load().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn upsert_resolves_session_from_pane_id() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "my work");
+        tmux.add_pane("my work", "%4");
+        let store = ContextStore::temp();
+
+        handle_upsert(
+            upsert_args(&["--pane-id", "%4", "--status", "waiting"]),
+            &tmux,
+            &store,
+        )
+        .unwrap();
+        handle_upsert(
+            upsert_args(&["--pane-id", "%4", "--session", "--context", "api", "work"]),
+            &tmux,
+            &store,
+        )
+        .unwrap();
+
+        let contexts = store.load().unwrap();
+        let entry = &contexts[&crate::context::session_key("my work")];
+        assert_eq!(entry.session_id.as_deref(), Some("$1"));
+        assert_eq!(entry.context.as_deref(), Some("api work"));
+        assert_eq!(
+            entry.panes["%4"].status,
+            Some(crate::context::AgentStatus::Waiting)
+        );
+        assert_eq!(
+            tmux.option("%4", crate::mirror::STATUS_OPTION).as_deref(),
+            Some("waiting")
+        );
+        assert_eq!(
+            tmux.option("=my work:", crate::mirror::CONTEXT_OPTION)
+                .as_deref(),
+            Some("api work")
+        );
+        assert!(handle_upsert(upsert_args(&["--pane-id", "%9"]), &tmux, &store).is_err());
+    }
+
+    fn switch_args(args: &[&str]) -> SwitchArgs {
+        let cli = Cli::try_parse_from(["jkl", "switch"].iter().chain(args)).unwrap();
+        match cli.command {
+            Commands::Switch(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn switch_targets_pane_window_and_session() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "my work");
+        tmux.add_pane("my work", "%4");
+
+        let current = Handoff::Switch { client: None };
+        let other = Handoff::Switch {
+            client: Some("/dev/pts/2".to_string()),
+        };
+        handle_switch(switch_args(&["--pane-id", "%4"]), &tmux, &current).unwrap();
+        handle_switch(switch_args(&["my", "work"]), &tmux, &other).unwrap();
+        handle_switch(
+            switch_args(&["--session-id", "$1"]),
+            &tmux,
+            &Handoff::Attach,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tmux.calls(),
+            vec![
+                "select-window @4",
+                "select-pane %4",
+                "switch-client $1",
+                "switch-client -c /dev/pts/2 $1",
+                "attach-session $1",
+            ]
+        );
+        assert!(handle_switch(switch_args(&["missing"]), &tmux, &current).is_err());
+        assert!(handle_switch(switch_args(&[]), &tmux, &current).is_err());
+    }
+
+    #[test]
+    fn list_args_keep_shell_quoted_phrases() {
+        let query = query_from_args(vec![
+            "status:waiting".to_string(),
+            "ctx:flaky test".to_string(),
+            "api".to_string(),
+        ]);
+        assert_eq!(query, r#"status:waiting "ctx:flaky test" api"#);
+        let parsed = crate::query::Query::parse(&query).unwrap();
+        assert_eq!(parsed.text(), "api");
+    }
+}
@@ -1,18 +1,19 @@
-use blake3;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum AgentStatus {
     Idle,
     Working,
     Waiting,
     Done,
+    Failed,
     None,
 }
 
@@ -23,6 +24,7 @@ impl std::fmt::Display for AgentStatus {
             AgentStatus::Working => "working",
             AgentStatus::Waiting => "waiting",
             AgentStatus::Done => "done",
+            AgentStatus::Failed => "failed",
             AgentStatus::None => "none",
         };
         formatter.write_str(text)
@@ -38,6 +40,7 @@ impl FromStr for AgentStatus {
             "working" => Ok(AgentStatus::Working),
             "waiting" => Ok(AgentStatus::Waiting),
             "done" => Ok(AgentStatus::Done),
+            "failed" => Ok(AgentStatus::Failed),
             "none" => Ok(AgentStatus::None),
             other => Err(StatusParseError(format!("Invalid status: {other}"))),
         }
@@ -59,6 +62,9 @@ impl std::error::Error for StatusParseError {}
 pub struct PaneContext {
     pub status: Option<AgentStatus>,
     pub context: Option<String>,
+    /// When the status or context last changed (unix seconds).
+    #[serde(default)]
+    pub updated_at: Option<u64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
@@ -70,114 +76,376 @@ pub struct SessionContext {
     pub context: Option<String>,
     #[serde(default)]
     pub panes: HashMap<String, PaneContext>,
+    /// When a client last switched to the session (unix seconds).
+    #[serde(default)]
+    pub seen_at: Option<u64>,
+    /// When the session's own status or context last changed (unix seconds).
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    /// Socket path of the tmux server the session was last seen on.
+    #[serde(default)]
+    pub server: Option<String>,
+}
+
+/// A session entry moved out of the store when its tmux session was killed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchivedSession {
+    pub archived_at: u64,
+    #[serde(flatten)]
+    pub session: SessionContext,
+}
+
+/// TUI preferences kept across launches, in `tui_settings.json` next to the
+/// store. Values are the TUI's own labels; unknown ones fall back to defaults.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// `asc` or `desc`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<String>,
 }
 
 pub fn session_key(session_name: &str) -> String {
     blake3::hash(session_name.as_bytes()).to_hex().to_string()
 }
 
-pub fn load_contexts() -> Result<HashMap<String, SessionContext>, Box<dyn Error>> {
-    let Some(path) = context_path() else {
-        return Ok(HashMap::new());
-    };
-    let contents = match fs::read_to_string(&path) {
-        Ok(contents) => contents,
-        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+/// The JSON file holding session and pane metadata.
+#[derive(Clone, Debug)]
+pub struct ContextStore {
+    path: Option<PathBuf>,
+    /// Removes the directory of a `temp` store once its last clone is gone.
+    #[cfg(test)]
+    _temp_dir: Option<std::sync::Arc<TempDir>>,
+}
+
+impl ContextStore {
+    /// The store under `~/.config/jkl`, or a no-op store when `HOME` is unset.
+    pub fn from_env() -> Self {
+        Self {
+            path: context_path(),
+            #[cfg(test)]
+            _temp_dir: None,
+        }
+    }
+
+    pub fn load(&self) -> Result<HashMap<String, SessionContext>, Box<dyn Error>> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(HashMap::new());
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, "{}")?;
+                "{}".to_string()
+            }
+            Err(error) => return Err(Box::new(error)),
+        };
+        let contexts = serde_json::from_str(&contents)?;
+        Ok(normalize_context_keys(contexts))
+    }
+
+    pub fn upsert_session(
+        &self,
+        session_name: String,
+        session_id: Option<String>,
+        status: Option<AgentStatus>,
+        context: Option<String>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.update(|contexts| {
+            let key = session_key(&session_name);
+            let entry = contexts.entry(key.clone()).or_default();
+            entry.session_name = Some(session_name);
+            if let Some(session_id) = session_id {
+                entry.session_id = Some(session_id);
+            }
+            if status.is_some() || context.is_some() {
+                entry.updated_at = Some(unix_now());
+            }
+            if status.is_some() {
+                entry.status = status;
+            }
+            if context.is_some() {
+                entry.context = context;
+            }
+            Ok(key)
+        })
+    }
+
+    /// Stores `entry` under its session name, replacing anything left behind
+    /// by an older session of the same name.
+    pub fn insert_session(&self, mut entry: SessionContext) -> Result<String, Box<dyn Error>> {
+        let session_name = entry
+            .session_name
+            .as_deref()
+            .ok_or("Missing session name")?;
+        let key = session_key(session_name);
+        entry.updated_at = entry.updated_at.or(Some(unix_now()));
+        self.update(|contexts| {
+            contexts.insert(key.clone(), entry);
+            Ok(key)
+        })
+    }
+
+    pub fn upsert_pane(
+        &self,
+        session_name: &str,
+        pane_id: &str,
+        status: Option<AgentStatus>,
+        context: Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.update(|contexts| {
+            let key = session_key(session_name);
+            let entry = contexts.entry(key).or_default();
+            entry.session_name = Some(session_name.to_string());
+            let pane = entry.panes.entry(pane_id.to_string()).or_default();
+            if status.is_some() || context.is_some() {
+                pane.updated_at = Some(unix_now());
+            }
+            if let Some(status) = status {
+                pane.status = Some(status);
+            }
+            if let Some(context) = context {
+                pane.context = Some(context);
+            }
+            Ok(())
+        })
+    }
+
+    pub fn rename_session(
+        &self,
+        session_id: &str,
+        session_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.update(|contexts| {
+            let mut extracted = None;
+            let mut old_key = None;
+            for (key, context) in contexts.iter() {
+                if context.session_id.as_deref() == Some(session_id) {
+                    old_key = Some(key.clone());
+                    extracted = Some(context.clone());
+                    break;
+                }
+            }
+            if let Some(old_key) = old_key {
+                contexts.remove(&old_key);
+            }
+            let mut entry = extracted.unwrap_or_default();
+            entry.session_name = Some(session_name.to_string());
+            entry.session_id = Some(session_id.to_string());
+            let new_key = session_key(session_name);
+            let target = contexts.entry(new_key).or_default();
+            merge_context(target, entry);
+            Ok(())
+        })
+    }
+
+    /// Records that a client switched to the session. Sessions without an
+    /// entry are left out rather than added, since jkl does not track them.
+    pub fn mark_seen(&self, session_name: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|contexts| {
+            if let Some(entry) = contexts.get_mut(&session_key(session_name)) {
+                entry.seen_at = Some(unix_now());
+            }
+            Ok(())
+        })
+    }
+
+    /// Records `server` as where the named sessions live, for those that
+    /// have an entry.
+    pub fn set_server(&self, session_names: &[String], server: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|contexts| {
+            for session_name in session_names {
+                if let Some(entry) = contexts.get_mut(&session_key(session_name)) {
+                    entry.server = Some(server.to_string());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Removes the context of a session, or of one of its panes.
+    pub fn clear_context(
+        &self,
+        session_name: &str,
+        pane_id: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.update(|contexts| {
+            let Some(entry) = contexts.get_mut(&session_key(session_name)) else {
+                return Ok(());
+            };
+            match pane_id {
+                Some(pane_id) => {
+                    if let Some(pane) = entry.panes.get_mut(pane_id)
+                        && pane.context.take().is_some()
+                    {
+                        pane.updated_at = Some(unix_now());
+                    }
+                }
+                None => {
+                    if entry.context.take().is_some() {
+                        entry.updated_at = Some(unix_now());
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub fn remove_pane(&self, session_name: &str, pane_id: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|contexts| {
+            if let Some(entry) = contexts.get_mut(&session_key(session_name)) {
+                entry.panes.remove(pane_id);
+            }
+            Ok(())
+        })
+    }
+
+    /// Moves a session's entry to the archive file next to the store,
+    /// returning whether there was one.
+    pub fn archive_session(&self, session_name: &str) -> Result<bool, Box<dyn Error>> {
+        self.update(|contexts| {
+            let Some(session) = contexts.remove(&session_key(session_name)) else {
+                return Ok(false);
+            };
+            if let Some(path) = self.archive_path() {
+                let line = serde_json::to_string(&ArchivedSession {
+                    archived_at: unix_now(),
+                    session,
+                })?;
+                let mut file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                writeln!(file, "{line}")?;
             }
-            fs::write(&path, "{}")?;
-            "{}".to_string()
+            Ok(true)
+        })
+    }
+
+    /// Sessions archived so far, oldest first.
+    #[cfg(test)]
+    pub fn archived(&self) -> Result<Vec<ArchivedSession>, Box<dyn Error>> {
+        let Some(path) = self.archive_path() else {
+            return Ok(Vec::new());
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(Box::new(error)),
+        };
+        let mut archived = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            archived.push(serde_json::from_str(line)?);
         }
-        Err(error) => return Err(Box::new(error)),
-    };
-    let contexts = serde_json::from_str(&contents)?;
-    Ok(normalize_context_keys(contexts))
-}
-
-pub fn upsert_session(
-    session_name: String,
-    session_id: Option<String>,
-    status: Option<AgentStatus>,
-    context: Option<String>,
-) -> Result<String, Box<dyn Error>> {
-    let mut contexts = load_contexts()?;
-    let key = session_key(&session_name);
-    let entry = contexts.entry(key.clone()).or_default();
-    entry.session_name = Some(session_name);
-    if let Some(session_id) = session_id {
-        entry.session_id = Some(session_id);
-    }
-    if status.is_some() {
-        entry.status = status;
-    }
-    if context.is_some() {
-        entry.context = context;
-    }
-    save_contexts(&contexts)?;
-    Ok(key)
-}
-
-pub fn upsert_pane(
-    session_name: &str,
-    pane_id: &str,
-    status: Option<AgentStatus>,
-    context: Option<String>,
-) -> Result<(), Box<dyn Error>> {
-    let mut contexts = load_contexts()?;
-    let key = session_key(session_name);
-    let entry = contexts.entry(key).or_default();
-    entry.session_name = Some(session_name.to_string());
-    let pane = entry.panes.entry(pane_id.to_string()).or_default();
-    if let Some(status) = status {
-        pane.status = Some(status);
-    }
-    if let Some(context) = context {
-        pane.context = Some(context);
-    }
-    save_contexts(&contexts)?;
-    Ok(())
-}
-
-pub fn rename_session(session_id: &str, session_name: &str) -> Result<(), Box<dyn Error>> {
-    let mut contexts = load_contexts()?;
-    let mut extracted = None;
-    let mut old_key = None;
-    for (key, context) in &contexts {
-        if context.session_id.as_deref() == Some(session_id) {
-            old_key = Some(key.clone());
-            extracted = Some(context.clone());
-            break;
+        Ok(archived)
+    }
+
+    /// Saved TUI settings, or defaults when none were saved yet.
+    pub fn settings(&self) -> Result<Settings, Box<dyn Error>> {
+        let Some(path) = self.settings_path() else {
+            return Ok(Settings::default());
+        };
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(Box::new(error)),
         }
     }
-    if let Some(old_key) = old_key {
-        contexts.remove(&old_key);
+
+    pub fn save_settings(&self, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        let Some(path) = self.settings_path() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(settings)?)?;
+        Ok(())
+    }
+
+    fn settings_path(&self) -> Option<PathBuf> {
+        self.path
+            .as_ref()
+            .map(|path| path.with_file_name("tui_settings.json"))
     }
-    let mut entry = extracted.unwrap_or_default();
-    entry.session_name = Some(session_name.to_string());
-    entry.session_id = Some(session_id.to_string());
-    let new_key = session_key(session_name);
-    let target = contexts.entry(new_key).or_default();
-    merge_context(target, entry);
-    save_contexts(&contexts)?;
-    Ok(())
-}
 
-pub fn prune_panes(live_panes: &HashMap<String, HashSet<String>>) -> Result<(), Box<dyn Error>> {
-    let mut contexts = load_contexts()?;
-    for context in contexts.values_mut() {
-        let Some(session_name) = context.session_name.as_ref() else {
-            continue;
+    fn archive_path(&self) -> Option<PathBuf> {
+        self.path
+            .as_ref()
+            .map(|path| path.with_file_name("session_archive.jsonl"))
+    }
+
+    pub fn prune_panes(
+        &self,
+        live_panes: &HashMap<String, HashSet<String>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.update(|contexts| {
+            for context in contexts.values_mut() {
+                let Some(session_name) = context.session_name.as_ref() else {
+                    continue;
+                };
+                let Some(live_ids) = live_panes.get(session_name) else {
+                    continue;
+                };
+                context
+                    .panes
+                    .retain(|pane_id, _| live_ids.contains(pane_id));
+            }
+            Ok(())
+        })
+    }
+
+    /// Loads the store, applies `change` and saves the result while holding
+    /// an exclusive lock on `session_context.json.lock`. Hooks run jkl in
+    /// the background, often several at once, and without the lock one
+    /// process would overwrite what another just saved.
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut HashMap<String, SessionContext>) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let _lock = self.lock()?;
+        let mut contexts = self.load()?;
+        let value = change(&mut contexts)?;
+        self.save(&contexts)?;
+        Ok(value)
+    }
+
+    /// The lock is released when the returned file is dropped.
+    fn lock(&self) -> Result<Option<fs::File>, Box<dyn Error>> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(None);
         };
-        let Some(live_ids) = live_panes.get(session_name) else {
-            continue;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension("json.lock"))?;
+        file.lock()?;
+        Ok(Some(file))
+    }
+
+    /// Writes to a temp file of this process's own and renames it over the
+    /// store, so readers never see a partial file.
+    fn save(&self, contexts: &HashMap<String, SessionContext>) -> Result<(), Box<dyn Error>> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
         };
-        context
-            .panes
-            .retain(|pane_id, _| live_ids.contains(pane_id));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_string_pretty(contexts)?;
+        let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
     }
-    save_contexts(&contexts)?;
-    Ok(())
 }
 
 fn normalize_context_keys(
@@ -203,12 +471,17 @@ fn merge_context(target: &mut SessionContext, source: SessionContext) {
     if target.session_id.is_none() {
         target.session_id = source.session_id;
     }
+    if target.server.is_none() {
+        target.server = source.server;
+    }
     if target.status.is_none() {
         target.status = source.status;
     }
     if target.context.is_none() {
         target.context = source.context;
     }
+    target.seen_at = target.seen_at.max(source.seen_at);
+    target.updated_at = target.updated_at.max(source.updated_at);
     for (pane_id, pane) in source.panes {
         let entry = target.panes.entry(pane_id).or_default();
         if entry.status.is_none() {
@@ -217,21 +490,15 @@ fn merge_context(target: &mut SessionContext, source: SessionContext) {
         if entry.context.is_none() {
             entry.context = pane.context;
         }
+        entry.updated_at = entry.updated_at.max(pane.updated_at);
     }
 }
 
-fn save_contexts(contexts: &HashMap<String, SessionContext>) -> Result<(), Box<dyn Error>> {
-    let Some(path) = context_path() else {
-        return Ok(());
-    };
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    let contents = serde_json::to_string_pretty(contexts)?;
-    let temp_path = path.with_extension("json.tmp");
-    fs::write(&temp_path, contents)?;
-    fs::rename(&temp_path, &path)?;
-    Ok(())
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
 }
 
 fn context_path() -> Option<PathBuf> {
@@ -239,3 +506,103 @@ fn context_path() -> Option<PathBuf> {
     let base_dir = PathBuf::from(home).join(".config");
     Some(base_dir.join("jkl").join("session_context.json"))
 }
+
+#[cfg(test)]
+impl ContextStore {
+    /// A store backed by a fresh file under the system temp directory.
+    pub(crate) fn temp() -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "jkl-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        Self {
+            path: Some(dir.join("session_context.json")),
+            _temp_dir: Some(std::sync::Arc::new(TempDir(dir))),
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_moves_entry_to_new_key() {
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "old".to_string(),
+                Some("$1".to_string()),
+                Some(AgentStatus::Working),
+                Some("ctx".to_string()),
+            )
+            .unwrap();
+        store
+            .upsert_pane("old", "%1", Some(AgentStatus::Done), None)
+            .unwrap();
+
+        store.rename_session("$1", "new").unwrap();
+
+        let contexts = store.load().unwrap();
+        assert!(!contexts.contains_key(&session_key("old")));
+        let entry = &contexts[&session_key("new")];
+        assert_eq!(entry.session_name.as_deref(), Some("new"));
+        assert_eq!(entry.status, Some(AgentStatus::Working));
+        assert_eq!(entry.panes["%1"].status, Some(AgentStatus::Done));
+    }
+
+    #[test]
+    fn prune_keeps_only_live_panes() {
+        let store = ContextStore::temp();
+        store
+            .upsert_pane("work", "%1", Some(AgentStatus::Working), None)
+            .unwrap();
+        store
+            .upsert_pane("work", "%2", Some(AgentStatus::Waiting), None)
+            .unwrap();
+        store
+            .upsert_pane("other", "%3", Some(AgentStatus::Idle), None)
+            .unwrap();
+
+        let live = HashMap::from([("work".to_string(), HashSet::from(["%2".to_string()]))]);
+        store.prune_panes(&live).unwrap();
+
+        let contexts = store.load().unwrap();
+        let work = &contexts[&session_key("work")];
+        assert!(!work.panes.contains_key("%1"));
+        assert!(work.panes.contains_key("%2"));
+        assert!(contexts[&session_key("other")].panes.contains_key("%3"));
+    }
+
+    #[test]
+    fn concurrent_updates_are_not_lost() {
+        let store = ContextStore::temp();
+        std::thread::scope(|scope| {
+            for index in 0..8 {
+                let store = &store;
+                scope.spawn(move || {
+                    store
+                        .upsert_pane("work", &format!("%{index}"), None, Some("ctx".to_string()))
+                        .unwrap();
+                });
+            }
+        });
+
+        assert_eq!(store.load().unwrap()[&session_key("work")].panes.len(), 8);
+    }
+}
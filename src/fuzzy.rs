@@ -0,0 +1,361 @@
+//! An in-process take on `fzf --filter`: the same extended search syntax and
+//! the scoring of fzf's v1 algorithm, so results rank the way fzf users expect.
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+const BONUS_CAMEL_123: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+const BONUS_BOUNDARY_WHITE: i32 = BONUS_BOUNDARY + 2;
+const BONUS_BOUNDARY_DELIMITER: i32 = BONUS_BOUNDARY + 1;
+
+/// Ordered like fzf's classes: everything after `NonWord` starts a word
+/// when it follows whitespace, a delimiter or punctuation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum CharClass {
+    White,
+    NonWord,
+    Delimiter,
+    Lower,
+    Upper,
+    Letter,
+    Number,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::White
+    } else if "/,:;|".contains(c) {
+        CharClass::Delimiter
+    } else if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Number
+    } else if c.is_alphabetic() {
+        CharClass::Letter
+    } else {
+        CharClass::NonWord
+    }
+}
+
+fn bonus_for(previous: CharClass, class: CharClass) -> i32 {
+    if class > CharClass::NonWord {
+        match previous {
+            CharClass::White => return BONUS_BOUNDARY_WHITE,
+            CharClass::Delimiter => return BONUS_BOUNDARY_DELIMITER,
+            CharClass::NonWord => return BONUS_BOUNDARY,
+            _ => {}
+        }
+    }
+    if (previous == CharClass::Lower && class == CharClass::Upper)
+        || (previous != CharClass::Number && class == CharClass::Number)
+    {
+        return BONUS_CAMEL_123;
+    }
+    match class {
+        CharClass::NonWord | CharClass::Delimiter => BONUS_NON_WORD,
+        CharClass::White => BONUS_BOUNDARY_WHITE,
+        _ => 0,
+    }
+}
+
+/// Where a query matched a text, and how well.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Match {
+    pub score: i32,
+    /// Char indices of the matched characters, ascending.
+    pub positions: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TermKind {
+    Fuzzy,
+    /// `'text`
+    Exact,
+    /// `^text`
+    Prefix,
+    /// `text$`
+    Suffix,
+    /// `^text$`
+    Equal,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Term {
+    kind: TermKind,
+    /// `!text`: the term matches texts that do not contain it.
+    inverse: bool,
+    pattern: Vec<char>,
+    /// Smart case: only patterns with an uppercase letter are case sensitive.
+    case_sensitive: bool,
+}
+
+impl Term {
+    fn parse(token: &str) -> Option<Self> {
+        let (inverse, token) = match token.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, token),
+        };
+        let (mut kind, mut token) = match token.strip_prefix('\'') {
+            Some(rest) => (TermKind::Exact, rest),
+            None => match token.strip_prefix('^') {
+                Some(rest) => (TermKind::Prefix, rest),
+                // Negated terms match exactly, as in fzf.
+                None if inverse => (TermKind::Exact, token),
+                None => (TermKind::Fuzzy, token),
+            },
+        };
+        if kind != TermKind::Exact
+            && let Some(rest) = token.strip_suffix('$')
+            && !rest.is_empty()
+        {
+            kind = if kind == TermKind::Prefix {
+                TermKind::Equal
+            } else {
+                TermKind::Suffix
+            };
+            token = rest;
+        }
+        if token.is_empty() {
+            return None;
+        }
+        let case_sensitive = token.chars().any(char::is_uppercase);
+        let pattern = token
+            .chars()
+            .map(|c| if case_sensitive { c } else { fold(c) })
+            .collect();
+        Some(Self {
+            kind,
+            inverse,
+            pattern,
+            case_sensitive,
+        })
+    }
+
+    fn find(&self, text: &[char]) -> Option<Match> {
+        let folded;
+        let text_cmp = if self.case_sensitive {
+            text
+        } else {
+            folded = text.iter().map(|c| fold(*c)).collect::<Vec<_>>();
+            folded.as_slice()
+        };
+        let pattern = self.pattern.as_slice();
+        let len = pattern.len();
+        if len > text.len() {
+            return None;
+        }
+        let at = |start: usize| (text_cmp[start..start + len] == pattern[..]).then_some(start);
+        let start = match self.kind {
+            TermKind::Fuzzy => return fuzzy_match(text, text_cmp, pattern),
+            TermKind::Exact => {
+                return (0..=text.len() - len)
+                    .filter_map(at)
+                    .map(|start| score(text, text_cmp, pattern, start, start + len))
+                    .max_by(|a, b| a.score.cmp(&b.score).then(b.positions.cmp(&a.positions)));
+            }
+            TermKind::Prefix => at(0)?,
+            TermKind::Suffix => at(text.len() - len)?,
+            TermKind::Equal if len == text.len() => at(0)?,
+            TermKind::Equal => return None,
+        };
+        Some(score(text, text_cmp, pattern, start, start + len))
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// fzf's v1 algorithm: the first occurrence of the pattern as a subsequence,
+/// then narrowed from its end backwards to the shortest such window.
+fn fuzzy_match(text: &[char], text_cmp: &[char], pattern: &[char]) -> Option<Match> {
+    let mut pattern_index = 0;
+    let mut end = None;
+    for (index, c) in text_cmp.iter().enumerate() {
+        if *c == pattern[pattern_index] {
+            pattern_index += 1;
+            if pattern_index == pattern.len() {
+                end = Some(index + 1);
+                break;
+            }
+        }
+    }
+    let end = end?;
+    let mut start = end;
+    let mut pattern_index = pattern.len();
+    while pattern_index > 0 {
+        start -= 1;
+        if text_cmp[start] == pattern[pattern_index - 1] {
+            pattern_index -= 1;
+        }
+    }
+    Some(score(text, text_cmp, pattern, start, end))
+}
+
+/// Scores the window `start..end`, which holds the pattern as a subsequence.
+fn score(text: &[char], text_cmp: &[char], pattern: &[char], start: usize, end: usize) -> Match {
+    let mut result = Match::default();
+    let mut pattern_index = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first_bonus = 0;
+    let mut previous = match start {
+        0 => CharClass::White,
+        start => char_class(text[start - 1]),
+    };
+    for index in start..end {
+        let class = char_class(text[index]);
+        if pattern_index < pattern.len() && text_cmp[index] == pattern[pattern_index] {
+            result.positions.push(index);
+            result.score += SCORE_MATCH;
+            let mut bonus = bonus_for(previous, class);
+            if consecutive == 0 {
+                first_bonus = bonus;
+            } else {
+                if bonus >= BONUS_BOUNDARY && bonus > first_bonus {
+                    first_bonus = bonus;
+                }
+                bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            result.score += if pattern_index == 0 {
+                bonus * BONUS_FIRST_CHAR_MULTIPLIER
+            } else {
+                bonus
+            };
+            in_gap = false;
+            consecutive += 1;
+            pattern_index += 1;
+        } else {
+            result.score += if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            in_gap = true;
+            consecutive = 0;
+            first_bonus = 0;
+        }
+        previous = class;
+    }
+    result
+}
+
+/// A parsed search: space-separated terms that must all match, where terms
+/// joined by `|` match when any of them does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    groups: Vec<Vec<Term>>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Self {
+        let mut groups: Vec<Vec<Term>> = Vec::new();
+        let mut or_next = false;
+        for token in query.split_whitespace() {
+            if token == "|" {
+                or_next = !groups.is_empty();
+                continue;
+            }
+            let Some(term) = Term::parse(token) else {
+                continue;
+            };
+            match groups.last_mut() {
+                Some(group) if or_next => group.push(term),
+                _ => groups.push(vec![term]),
+            }
+            or_next = false;
+        }
+        Self { groups }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// The combined score and positions, or `None` when a term rules the
+    /// text out. An empty query matches everything with score 0.
+    pub fn matches(&self, text: &str) -> Option<Match> {
+        let text = text.chars().collect::<Vec<_>>();
+        let mut result = Match::default();
+        for group in &self.groups {
+            let found = group.iter().find_map(|term| match term.find(&text) {
+                Some(found) if !term.inverse => Some(found),
+                None if term.inverse => Some(Match::default()),
+                _ => None,
+            })?;
+            result.score += found.score;
+            result.positions.extend(found.positions);
+        }
+        result.positions.sort_unstable();
+        result.positions.dedup();
+        Some(result)
+    }
+}
+
+/// Indices of the texts matching `query`, best first: by score, then shorter
+/// texts, then original order, like fzf's default tiebreak.
+pub fn filter<S: AsRef<str>>(query: &Query, texts: &[S]) -> Vec<(usize, Match)> {
+    let mut matched = texts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, text)| Some((index, query.matches(text.as_ref())?)))
+        .collect::<Vec<_>>();
+    matched.sort_by_key(|(index, found)| {
+        (
+            std::cmp::Reverse(found.score),
+            texts[*index].as_ref().chars().count(),
+            *index,
+        )
+    });
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(query: &str, texts: &[&str]) -> Vec<String> {
+        filter(&Query::parse(query), texts)
+            .into_iter()
+            .map(|(index, _)| texts[index].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn scores_like_fzf() {
+        let query = Query::parse("api");
+        assert_eq!(
+            query.matches("api").unwrap().score,
+            16 * 3 + 10 * 2 + 10 + 10
+        );
+        let gap = Query::parse("ab").matches("a-b").unwrap();
+        assert_eq!(gap.positions, [0, 2]);
+        assert_eq!(gap.score, 16 * 2 + 10 * 2 - 3 + 8);
+        assert_eq!(
+            ranked("fb", &["foobar", "foo-bar", "fooBar", "xfb"]),
+            ["fooBar", "foo-bar", "foobar", "xfb"]
+        );
+        // Smart case.
+        assert!(Query::parse("API").matches("api").is_none());
+        assert!(Query::parse("api").matches("API v2").is_some());
+    }
+
+    #[test]
+    fn supports_extended_search_syntax() {
+        let texts = ["api-server", "web api", "web", "apiary"];
+        assert_eq!(ranked("'pi-", &texts), ["api-server"]);
+        assert_eq!(ranked("^web", &texts), ["web", "web api"]);
+        assert_eq!(ranked("api$", &texts), ["web api"]);
+        assert_eq!(ranked("^web$", &texts), ["web"]);
+        assert_eq!(ranked("!web", &texts), ["apiary", "api-server"]);
+        assert_eq!(ranked("^web | ary", &texts).len(), 3);
+        assert_eq!(ranked("api !server !^web", &texts), ["apiary"]);
+        assert!(Query::parse("  ! ^ ").is_empty());
+    }
+}
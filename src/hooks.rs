@@ -0,0 +1,210 @@
+use crate::tmux::{HookEntry, TmuxClient};
+use std::error::Error;
+use std::path::Path;
+
+/// The global hooks jkl keeps the store in sync with, and the jkl arguments
+/// each one runs. `#{q:...}` makes tmux shell-quote the value. Panes kept by
+/// `remain-on-exit` fire `pane-died` instead of `pane-exited`.
+pub const HOOKS: [(&str, &str); 5] = [
+    (
+        "session-renamed",
+        "rename #{q:hook_session} #{q:hook_session_name}",
+    ),
+    ("session-closed", "gc --session=#{q:hook_session_name}"),
+    // tmux leaves `hook_session_name` empty for pane hooks, and an exited
+    // pane's `session_name` is some other session, so jkl looks it up.
+    ("pane-exited", "gc --pane=#{hook_pane}"),
+    ("pane-died", "gc --pane=#{hook_pane}"),
+    (
+        "client-session-changed",
+        "seen --control-mode #{client_control_mode} #{q:session_name}",
+    ),
+];
+
+/// How one hook compares to what `install` would set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookState {
+    Installed,
+    Missing,
+    /// jkl entries that run another binary or older arguments.
+    Drifted(Vec<HookEntry>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HookStatus {
+    pub name: &'static str,
+    pub expected: String,
+    pub state: HookState,
+}
+
+/// Compares the server's global hooks with the ones `exe` should run.
+pub fn status(tmux: &dyn TmuxClient, exe: &Path) -> Result<Vec<HookStatus>, Box<dyn Error>> {
+    let hooks = tmux.list_hooks()?;
+    Ok(HOOKS
+        .iter()
+        .map(|(name, args)| {
+            let expected = hook_command(exe, args);
+            let ours = hooks
+                .iter()
+                .filter(|hook| hook.name == *name && is_jkl_entry(hook, args))
+                .collect::<Vec<_>>();
+            let state = if ours.iter().any(|hook| hook.command == expected) {
+                HookState::Installed
+            } else if ours.is_empty() {
+                HookState::Missing
+            } else {
+                HookState::Drifted(ours.into_iter().cloned().collect())
+            };
+            HookStatus {
+                name,
+                expected,
+                state,
+            }
+        })
+        .collect())
+}
+
+/// Registers every hook for `exe`, replacing drifted jkl entries and leaving
+/// other commands on the same hooks alone.
+pub fn install(tmux: &dyn TmuxClient, exe: &Path) -> Result<Vec<HookStatus>, Box<dyn Error>> {
+    let hooks = tmux.list_hooks()?;
+    for status in status(tmux, exe)? {
+        match &status.state {
+            HookState::Installed => continue,
+            HookState::Missing => {}
+            HookState::Drifted(entries) => {
+                for entry in entries {
+                    tmux.set_hook(&entry.name, entry.index, None)?;
+                }
+            }
+        }
+        let stale = match &status.state {
+            HookState::Drifted(entries) => entries.as_slice(),
+            _ => &[],
+        };
+        let index = hooks
+            .iter()
+            .filter(|hook| hook.name == status.name && !stale.contains(hook))
+            .map(|hook| hook.index + 1)
+            .max()
+            .unwrap_or(0);
+        tmux.set_hook(status.name, index, Some(&status.expected))?;
+    }
+    self::status(tmux, exe)
+}
+
+/// Removes every jkl entry, whichever binary it runs.
+pub fn uninstall(tmux: &dyn TmuxClient) -> Result<usize, Box<dyn Error>> {
+    let hooks = tmux.list_hooks()?;
+    let mut removed = 0;
+    for (name, args) in HOOKS {
+        for hook in hooks
+            .iter()
+            .filter(|hook| hook.name == name && is_jkl_entry(hook, args))
+        {
+            tmux.set_hook(&hook.name, hook.index, None)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// `run-shell` runs through `sh -c`, so the path is single-quoted.
+fn hook_command(exe: &Path, args: &str) -> String {
+    let path = exe.to_string_lossy().replace('\'', r"'\''");
+    format!("run-shell -b \"'{path}' {args}\"")
+}
+
+/// A `run-shell` entry calling some `jkl*` binary with the same subcommand,
+/// including the `jkl rename` hook `jkl.tmux` used to set.
+fn is_jkl_entry(hook: &HookEntry, args: &str) -> bool {
+    let subcommand = args.split(' ').next().unwrap_or_default();
+    hook.command.starts_with("run-shell")
+        && hook
+            .command
+            .split(['"', '\'', ' '])
+            .filter(|token| !token.is_empty())
+            .collect::<Vec<_>>()
+            .windows(2)
+            .any(|pair| {
+                let program = Path::new(pair[0])
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                program.starts_with("jkl") && pair[1] == subcommand
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tmux::fake::FakeTmux;
+
+    #[test]
+    fn install_replaces_drifted_entries_and_keeps_others() {
+        let tmux = FakeTmux::new();
+        tmux.set_hook(
+            "session-renamed",
+            0,
+            Some("run-shell \"jkl rename '#{hook_session}' '#{hook_session_name}'\""),
+        )
+        .unwrap();
+        tmux.set_hook("session-renamed", 1, Some("display-message renamed"))
+            .unwrap();
+        let exe = Path::new("/opt/bin/jkl");
+
+        let before = status(&tmux, exe).unwrap();
+        assert!(matches!(before[0].state, HookState::Drifted(_)));
+        assert!(
+            before[1..]
+                .iter()
+                .all(|hook| hook.state == HookState::Missing)
+        );
+
+        let after = install(&tmux, exe).unwrap();
+        assert!(after.iter().all(|hook| hook.state == HookState::Installed));
+        let hooks = tmux.list_hooks().unwrap();
+        let renamed = hooks
+            .iter()
+            .filter(|hook| hook.name == "session-renamed")
+            .map(|hook| (hook.index, hook.command.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            renamed,
+            vec![
+                (1, "display-message renamed"),
+                (
+                    2,
+                    "run-shell -b \"'/opt/bin/jkl' rename #{q:hook_session} #{q:hook_session_name}\""
+                ),
+            ]
+        );
+
+        let installed = hooks
+            .iter()
+            .filter(|hook| hook.command.contains("/opt/bin/jkl"))
+            .map(|hook| hook.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            installed,
+            vec![
+                "client-session-changed",
+                "pane-died",
+                "pane-exited",
+                "session-closed",
+                "session-renamed",
+            ]
+        );
+        assert!(hooks.iter().any(|hook| hook.name == "pane-died"
+            && hook.command == "run-shell -b \"'/opt/bin/jkl' gc --pane=#{hook_pane}\""));
+
+        let moved = status(&tmux, Path::new("/usr/local/bin/jkl2")).unwrap();
+        assert!(
+            moved
+                .iter()
+                .all(|hook| matches!(hook.state, HookState::Drifted(_)))
+        );
+        assert_eq!(uninstall(&tmux).unwrap(), 5);
+        assert_eq!(tmux.list_hooks().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::context::AgentStatus;
+use crate::tmux::TmuxPane;
+
+/// Window activity this recent suggests the agent is producing output.
+const WORKING_WITHIN_SECS: u64 = 30;
+/// Window activity this old suggests nothing is happening.
+const IDLE_AFTER_SECS: u64 = 10 * 60;
+
+/// Suggests a status for a pane from tmux signals alone.
+///
+/// Dead panes are `done` or `failed` by exit status, a bell means the agent
+/// wants attention, and otherwise the window's silence flag or activity age
+/// decides between `working` and `idle`.
+pub fn pane_status(pane: &TmuxPane, now: u64) -> Option<AgentStatus> {
+    if pane.dead {
+        return Some(match pane.dead_status {
+            Some(0) => AgentStatus::Done,
+            _ => AgentStatus::Failed,
+        });
+    }
+    if pane.bell {
+        return Some(AgentStatus::Waiting);
+    }
+    if pane.silence {
+        return Some(AgentStatus::Idle);
+    }
+    let elapsed = now.saturating_sub(pane.activity?);
+    if elapsed <= WORKING_WITHIN_SECS {
+        Some(AgentStatus::Working)
+    } else if elapsed >= IDLE_AFTER_SECS {
+        Some(AgentStatus::Idle)
+    } else {
+        None
+    }
+}
+
+/// The most pressing suggestion among a session's panes.
+pub fn session_status<'a>(
+    statuses: impl IntoIterator<Item = &'a AgentStatus>,
+) -> Option<AgentStatus> {
+    statuses
+        .into_iter()
+        .min_by_key(|status| urgency(status))
+        .cloned()
+}
+
+/// Lower is more pressing.
+pub fn urgency(status: &AgentStatus) -> u8 {
+    match status {
+        AgentStatus::Failed => 0,
+        AgentStatus::Waiting => 1,
+        AgentStatus::Working => 2,
+        AgentStatus::Idle => 3,
+        AgentStatus::Done => 4,
+        AgentStatus::None => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(activity: Option<u64>) -> TmuxPane {
+        TmuxPane {
+            activity,
+            ..TmuxPane::default()
+        }
+    }
+
+    #[test]
+    fn infers_from_tmux_signals() {
+        let now = 10_000;
+        let dead = |status| TmuxPane {
+            dead: true,
+            dead_status: status,
+            ..pane(Some(now))
+        };
+        assert_eq!(pane_status(&dead(Some(0)), now), Some(AgentStatus::Done));
+        assert_eq!(pane_status(&dead(Some(2)), now), Some(AgentStatus::Failed));
+        let bell = TmuxPane {
+            bell: true,
+            ..pane(Some(now))
+        };
+        assert_eq!(pane_status(&bell, now), Some(AgentStatus::Waiting));
+        assert_eq!(
+            pane_status(&pane(Some(now - 5)), now),
+            Some(AgentStatus::Working)
+        );
+        assert_eq!(pane_status(&pane(Some(now - 120)), now), None);
+        assert_eq!(
+            pane_status(&pane(Some(now - 3600)), now),
+            Some(AgentStatus::Idle)
+        );
+        assert_eq!(pane_status(&pane(None), now), None);
+    }
+
+    #[test]
+    fn session_takes_most_urgent_pane() {
+        let statuses = [
+            AgentStatus::Idle,
+            AgentStatus::Waiting,
+            AgentStatus::Working,
+        ];
+        assert_eq!(session_status(&statuses), Some(AgentStatus::Waiting));
+        assert_eq!(session_status(&[]), None);
+    }
+}
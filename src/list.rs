@@ -0,0 +1,235 @@
+use crate::context::{AgentStatus, ContextStore, SessionContext, session_key, unix_now};
+use crate::query::{Query, Subject};
+use crate::tmux::Snapshot;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// What `jkl list` prints.
+#[derive(Clone, Debug, Default)]
+pub struct ListOptions {
+    /// Same syntax as the TUI search, e.g. `status:waiting api`.
+    pub query: String,
+    /// List every pane of a matching session, not just the matching ones.
+    pub panes: bool,
+}
+
+/// One line per matching session, `name<TAB>status<TAB>context`, followed by
+/// its matching panes as indented `pane_id<TAB>status<TAB>context` lines.
+/// Sessions rank like the TUI search: by their best match when the query has
+/// free text, otherwise in tmux order.
+pub fn render(
+    store: &ContextStore,
+    snapshot: &Snapshot,
+    options: &ListOptions,
+) -> Result<String, Box<dyn Error>> {
+    let query = Query::parse(&options.query)?;
+    let contexts = store.load()?;
+    Ok(render_with(
+        &contexts,
+        snapshot,
+        &query,
+        options.panes,
+        unix_now(),
+    ))
+}
+
+fn render_with(
+    contexts: &HashMap<String, SessionContext>,
+    snapshot: &Snapshot,
+    query: &Query,
+    all_panes: bool,
+    now: u64,
+) -> String {
+    // Candidates are searched as `render` prints them; `owners` maps each
+    // back to its session and pane.
+    let mut candidates = Vec::new();
+    let mut owners = Vec::new();
+    for (session_index, session) in snapshot.sessions.iter().enumerate() {
+        let name = &session.session.name;
+        let entry = contexts.get(&session_key(name));
+        let subject = Subject {
+            session_name: name,
+            pane_id: None,
+            status: entry.and_then(|entry| entry.status.as_ref()),
+            context: entry.and_then(|entry| entry.context.as_deref()),
+            updated_at: entry.and_then(|entry| entry.updated_at),
+        };
+        if query.filters_match(&subject, now) {
+            candidates.push(line(name, &subject));
+            owners.push((session_index, None));
+        }
+        for pane in session.panes() {
+            let stored = entry.and_then(|entry| entry.panes.get(&pane.pane_id));
+            let subject = Subject {
+                session_name: name,
+                pane_id: Some(&pane.pane_id),
+                status: stored.and_then(|pane| pane.status.as_ref()),
+                context: stored.and_then(|pane| pane.context.as_deref()),
+                updated_at: stored.and_then(|pane| pane.updated_at),
+            };
+            // Without a query, sessions are listed without their panes.
+            if !query.is_empty() && query.filters_match(&subject, now) {
+                let text = format!(
+                    "{}\t{}\t{}",
+                    line(&pane.pane_id, &subject),
+                    pane.current_command,
+                    pane.current_path
+                );
+                candidates.push(text);
+                owners.push((session_index, Some(pane.pane_id.clone())));
+            }
+        }
+    }
+
+    let mut ranked: Vec<usize> = Vec::new();
+    let mut matched_panes: HashMap<usize, Vec<String>> = HashMap::new();
+    let order: Vec<usize> = if query.fuzzy().is_empty() {
+        (0..candidates.len()).collect()
+    } else {
+        crate::fuzzy::filter(query.fuzzy(), &candidates)
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect()
+    };
+    for index in order {
+        let (session_index, pane_id) = &owners[index];
+        if !ranked.contains(session_index) {
+            ranked.push(*session_index);
+        }
+        if let Some(pane_id) = pane_id {
+            matched_panes
+                .entry(*session_index)
+                .or_default()
+                .push(pane_id.clone());
+        }
+    }
+
+    let mut output = String::new();
+    for session_index in ranked {
+        let session = &snapshot.sessions[session_index];
+        let name = &session.session.name;
+        let entry = contexts.get(&session_key(name));
+        let subject = Subject {
+            session_name: name,
+            status: entry.and_then(|entry| entry.status.as_ref()),
+            context: entry.and_then(|entry| entry.context.as_deref()),
+            ..Subject::default()
+        };
+        output.push_str(&line(name, &subject));
+        output.push('\n');
+        let matched = matched_panes.remove(&session_index).unwrap_or_default();
+        for pane in session.panes() {
+            if !all_panes && !matched.contains(&pane.pane_id) {
+                continue;
+            }
+            let stored = entry.and_then(|entry| entry.panes.get(&pane.pane_id));
+            let subject = Subject {
+                status: stored.and_then(|pane| pane.status.as_ref()),
+                context: stored.and_then(|pane| pane.context.as_deref()),
+                ..Subject::default()
+            };
+            output.push_str("  ");
+            output.push_str(&line(&pane.pane_id, &subject));
+            output.push('\n');
+        }
+    }
+    output
+}
+
+fn line(label: &str, subject: &Subject) -> String {
+    let status = subject
+        .status
+        .filter(|status| **status != AgentStatus::None)
+        .map(AgentStatus::to_string)
+        .unwrap_or_else(|| "-".to_string());
+    let context = subject
+        .context
+        .map(str::trim)
+        .filter(|context| !context.is_empty())
+        .unwrap_or("-");
+    format!("{label}\t{status}\t{context}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tmux::{TmuxPane, TmuxSession};
+
+    fn snapshot() -> Snapshot {
+        let session = |id: &str, name: &str| TmuxSession {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..TmuxSession::default()
+        };
+        let pane = |session_name: &str, pane_id: &str, index: u32| TmuxPane {
+            session_name: session_name.to_string(),
+            pane_id: pane_id.to_string(),
+            window_id: "@1".to_string(),
+            pane_index: index,
+            ..TmuxPane::default()
+        };
+        Snapshot::new(
+            vec![session("$1", "api"), session("$2", "web")],
+            vec![
+                pane("api", "%1", 0),
+                pane("api", "%2", 1),
+                pane("web", "%3", 0),
+            ],
+        )
+    }
+
+    fn list(store: &ContextStore, query: &str, panes: bool) -> String {
+        let options = ListOptions {
+            query: query.to_string(),
+            panes,
+        };
+        render(store, &snapshot(), &options).unwrap()
+    }
+
+    #[test]
+    fn lists_sessions_and_matching_panes() {
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "web".to_string(),
+                None,
+                Some(AgentStatus::Working),
+                Some("deploy".to_string()),
+            )
+            .unwrap();
+        store
+            .upsert_pane(
+                "api",
+                "%2",
+                Some(AgentStatus::Waiting),
+                Some("flaky test".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(list(&store, "", false), "api\t-\t-\nweb\tworking\tdeploy\n");
+        assert_eq!(
+            list(&store, "", true),
+            "api\t-\t-\n  %1\t-\t-\n  %2\twaiting\tflaky test\nweb\tworking\tdeploy\n  %3\t-\t-\n"
+        );
+        assert_eq!(
+            list(&store, r#"status:waiting ctx:"flaky test""#, false),
+            "api\t-\t-\n  %2\twaiting\tflaky test\n"
+        );
+        assert_eq!(list(&store, "deploy", false), "web\tworking\tdeploy\n");
+        assert_eq!(
+            list(&store, "updated:>1h", false),
+            "api\t-\t-\n  %1\t-\t-\nweb\tworking\tdeploy\n  %3\t-\t-\n"
+        );
+        assert!(
+            render(
+                &store,
+                &snapshot(),
+                &ListOptions {
+                    query: "status:nope".to_string(),
+                    panes: false,
+                }
+            )
+            .is_err()
+        );
+    }
+}
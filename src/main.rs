@@ -1,8 +1,27 @@
+use std::process::ExitCode;
+
+mod actions;
 mod cli;
 mod context;
+mod fuzzy;
+mod hooks;
+mod infer;
+mod list;
+mod mirror;
+mod query;
+mod status_line;
 mod tmux;
 mod tui;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    cli::run()
+fn main() -> ExitCode {
+    match cli::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("jkl: {error}");
+            let code = error
+                .downcast_ref::<tmux::TmuxError>()
+                .map_or(1, tmux::TmuxError::exit_code);
+            ExitCode::from(code)
+        }
+    }
 }
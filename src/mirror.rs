@@ -0,0 +1,164 @@
+use crate::context::{AgentStatus, ContextStore, session_key};
+use crate::tmux::{OptionScope, TmuxClient, UserOption};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// User options carrying jkl metadata, for use in tmux formats such as
+/// `#{@jkl_status}`.
+pub const STATUS_OPTION: &str = "@jkl_status";
+pub const CONTEXT_OPTION: &str = "@jkl_context";
+
+/// Copies a session's stored status and context onto the tmux session.
+pub fn session(
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+    session_name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let contexts = store.load()?;
+    let entry = contexts.get(&session_key(session_name));
+    tmux.set_options(&user_options(
+        OptionScope::Session(session_target(session_name)),
+        entry.and_then(|entry| entry.status.as_ref()),
+        entry.and_then(|entry| entry.context.as_deref()),
+    ))?;
+    Ok(())
+}
+
+/// Copies a pane's stored status and context onto the tmux pane.
+pub fn pane(
+    tmux: &dyn TmuxClient,
+    store: &ContextStore,
+    session_name: &str,
+    pane_id: &str,
+) -> Result<(), Box<dyn Error>> {
+    let contexts = store.load()?;
+    let entry = contexts
+        .get(&session_key(session_name))
+        .and_then(|entry| entry.panes.get(pane_id));
+    tmux.set_options(&user_options(
+        OptionScope::Pane(pane_id.to_string()),
+        entry.and_then(|entry| entry.status.as_ref()),
+        entry.and_then(|entry| entry.context.as_deref()),
+    ))?;
+    Ok(())
+}
+
+/// Sets or clears the options on every live session and pane to match the
+/// store, in a single tmux call. Returns how many targets were updated.
+pub fn sync(tmux: &dyn TmuxClient, store: &ContextStore) -> Result<usize, Box<dyn Error>> {
+    let contexts = store.load()?;
+    let snapshot = tmux.snapshot()?;
+    let entries = snapshot
+        .sessions()
+        .map(|session| {
+            (
+                session.name.as_str(),
+                contexts.get(&session_key(&session.name)),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut options = Vec::new();
+    for session in snapshot.sessions() {
+        let entry = entries.get(session.name.as_str()).copied().flatten();
+        options.extend(user_options(
+            OptionScope::Session(session_target(&session.name)),
+            entry.and_then(|entry| entry.status.as_ref()),
+            entry.and_then(|entry| entry.context.as_deref()),
+        ));
+    }
+    for pane in snapshot.panes() {
+        let entry = entries
+            .get(pane.session_name.as_str())
+            .copied()
+            .flatten()
+            .and_then(|entry| entry.panes.get(&pane.pane_id));
+        options.extend(user_options(
+            OptionScope::Pane(pane.pane_id.clone()),
+            entry.and_then(|entry| entry.status.as_ref()),
+            entry.and_then(|entry| entry.context.as_deref()),
+        ));
+    }
+    tmux.set_options(&options)?;
+    Ok(snapshot.sessions.len() + snapshot.panes().count())
+}
+
+/// Sessions are addressed by exact name, which stays valid across server
+/// restarts where stored ids do not.
+fn session_target(session_name: &str) -> String {
+    format!("={session_name}:")
+}
+
+fn user_options(
+    scope: OptionScope,
+    status: Option<&AgentStatus>,
+    context: Option<&str>,
+) -> [UserOption; 2] {
+    [
+        UserOption {
+            scope: scope.clone(),
+            name: STATUS_OPTION.to_string(),
+            value: status.map(ToString::to_string),
+        },
+        UserOption {
+            scope,
+            name: CONTEXT_OPTION.to_string(),
+            value: context.map(str::to_string),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tmux::fake::FakeTmux;
+
+    #[test]
+    fn sync_sets_and_clears_options_for_live_targets() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.add_pane("api", "%1");
+        tmux.add_pane("api", "%2");
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "api".to_string(),
+                None,
+                Some(AgentStatus::Waiting),
+                Some("review".to_string()),
+            )
+            .unwrap();
+        store
+            .upsert_pane("api", "%2", Some(AgentStatus::Working), None)
+            .unwrap();
+        store
+            .upsert_session("gone".to_string(), None, Some(AgentStatus::Done), None)
+            .unwrap();
+        tmux.set_options(&user_options(
+            OptionScope::Pane("%1".to_string()),
+            Some(&AgentStatus::Failed),
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(sync(&tmux, &store).unwrap(), 3);
+
+        assert_eq!(
+            tmux.option("=api:", STATUS_OPTION).as_deref(),
+            Some("waiting")
+        );
+        assert_eq!(
+            tmux.option("=api:", CONTEXT_OPTION).as_deref(),
+            Some("review")
+        );
+        assert_eq!(tmux.option("%2", STATUS_OPTION).as_deref(), Some("working"));
+        assert_eq!(tmux.option("%1", STATUS_OPTION), None);
+
+        store
+            .upsert_pane("api", "%1", None, Some("tests".to_string()))
+            .unwrap();
+        pane(&tmux, &store, "api", "%1").unwrap();
+        assert_eq!(tmux.option("%1", CONTEXT_OPTION).as_deref(), Some("tests"));
+        assert!(session(&tmux, &store, "gone").is_err());
+    }
+}
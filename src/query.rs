@@ -0,0 +1,260 @@
+//! The search language of the TUI and `jkl list`: field filters such as
+//! `status:waiting` or `updated:<10m`, with the remaining words matched
+//! fuzzily (see `crate::fuzzy`).
+
+use crate::context::AgentStatus;
+use crate::fuzzy;
+use std::fmt;
+
+/// A session or pane as the field filters see it. A pane has its session's
+/// name and its own status, context and update time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Subject<'a> {
+    pub session_name: &'a str,
+    pub pane_id: Option<&'a str>,
+    pub status: Option<&'a AgentStatus>,
+    pub context: Option<&'a str>,
+    pub updated_at: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Field {
+    /// `status:waiting,failed`
+    Status(Vec<AgentStatus>),
+    /// `name:api`, a case-insensitive substring of the session name.
+    Name(String),
+    /// `pane:%4` or `pane:4`
+    Pane(String),
+    /// `ctx:"flaky test"`, a case-insensitive substring of the context.
+    Context(String),
+    /// `updated:<10m` (within the last 10 minutes) or `updated:>1h`.
+    Updated { within: bool, seconds: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Filter {
+    field: Field,
+    /// `!status:done`
+    negate: bool,
+}
+
+impl Filter {
+    fn parse(token: &str) -> Result<Option<Self>, QueryError> {
+        let (negate, rest) = match token.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, token),
+        };
+        let Some((name, value)) = rest.split_once(':') else {
+            return Ok(None);
+        };
+        let field = match name.to_lowercase().as_str() {
+            "status" => Field::Status(
+                value
+                    .split(',')
+                    .filter(|status| !status.is_empty())
+                    .map(|status| {
+                        status
+                            .parse()
+                            .map_err(|error| QueryError(format!("{error}")))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            "name" => Field::Name(value.to_lowercase()),
+            "pane" if value.starts_with('%') => Field::Pane(value.to_string()),
+            "pane" => Field::Pane(format!("%{value}")),
+            "ctx" | "context" => Field::Context(value.to_lowercase()),
+            "updated" => parse_updated(value)?,
+            // Anything else is left to fuzzy matching, e.g. a path with `:`.
+            _ => return Ok(None),
+        };
+        Ok(Some(Self { field, negate }))
+    }
+
+    fn matches(&self, subject: &Subject, now: u64) -> bool {
+        let matched = match &self.field {
+            Field::Status(statuses) => subject
+                .status
+                .is_some_and(|status| statuses.contains(status)),
+            Field::Name(name) => subject.session_name.to_lowercase().contains(name),
+            Field::Pane(pane_id) => subject.pane_id == Some(pane_id.as_str()),
+            Field::Context(context) => subject
+                .context
+                .is_some_and(|value| value.to_lowercase().contains(context)),
+            Field::Updated { within, seconds } => {
+                let recent = subject
+                    .updated_at
+                    .is_some_and(|updated_at| now.saturating_sub(updated_at) <= *seconds);
+                recent == *within
+            }
+        };
+        matched != self.negate
+    }
+}
+
+fn parse_updated(value: &str) -> Result<Field, QueryError> {
+    let (within, duration) = match value.strip_prefix('>') {
+        Some(rest) => (false, rest),
+        None => (true, value.strip_prefix('<').unwrap_or(value)),
+    };
+    let invalid = || {
+        QueryError(format!(
+            "Invalid duration {value:?}; expected e.g. <10m, >2h or 30s"
+        ))
+    };
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (amount, unit) = duration.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit = match unit {
+        "s" => 1,
+        "" | "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(invalid()),
+    };
+    Ok(Field::Updated {
+        within,
+        seconds: amount.saturating_mul(unit),
+    })
+}
+
+/// Why a query could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryError(String);
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Field filters that must all hold, plus fuzzy free text.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    filters: Vec<Filter>,
+    text: String,
+    fuzzy: fuzzy::Query,
+}
+
+impl Query {
+    /// Words are split on whitespace outside double quotes, so a filter value
+    /// can hold spaces: `ctx:"flaky test"`. Words of the form `field:value`
+    /// with a known field (`status`, `name`, `pane`, `ctx`, `updated`) are
+    /// filters, anything else free text.
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let mut filters = Vec::new();
+        let mut words = Vec::new();
+        for token in tokenize(query) {
+            match Filter::parse(&token)? {
+                Some(filter) => filters.push(filter),
+                None => words.push(token),
+            }
+        }
+        let text = words.join(" ");
+        Ok(Self {
+            filters,
+            fuzzy: fuzzy::Query::parse(&text),
+            text,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.fuzzy.is_empty()
+    }
+
+    /// Whether every field filter holds for `subject`.
+    pub fn filters_match(&self, subject: &Subject, now: u64) -> bool {
+        self.filters
+            .iter()
+            .all(|filter| filter.matches(subject, now))
+    }
+
+    /// The free text, for handing to `fzf`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn fuzzy(&self) -> &fuzzy::Query {
+        &self.fuzzy
+    }
+}
+
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subject<'a>(status: Option<&'a AgentStatus>, context: Option<&'a str>) -> Subject<'a> {
+        Subject {
+            session_name: "API server",
+            pane_id: Some("%4"),
+            status,
+            context,
+            updated_at: Some(1_000),
+        }
+    }
+
+    #[test]
+    fn parses_field_filters_and_free_text() {
+        let query = Query::parse(r#"status:waiting,failed ctx:"flaky test" web !^old"#).unwrap();
+        assert_eq!(query.filters.len(), 2);
+        assert_eq!(query.text(), "web !^old");
+        let waiting = AgentStatus::Waiting;
+        assert!(query.filters_match(&subject(Some(&waiting), Some("Flaky test in CI")), 1_000));
+        assert!(!query.filters_match(&subject(Some(&waiting), Some("flaky")), 1_000));
+        assert!(!query.filters_match(&subject(None, Some("flaky test")), 1_000));
+
+        let query = Query::parse("name:api pane:4 !status:done").unwrap();
+        assert!(query.filters_match(&subject(None, None), 0));
+        let done = AgentStatus::Done;
+        assert!(!query.filters_match(&subject(Some(&done), None), 0));
+
+        assert!(Query::parse("status:bogus").is_err());
+        assert!(Query::parse("updated:<soon").is_err());
+        assert_eq!(
+            Query::parse("http://host:80").unwrap().text(),
+            "http://host:80"
+        );
+        assert!(Query::parse("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn updated_filters_compare_against_now() {
+        let recent = Query::parse("updated:<10m").unwrap();
+        let stale = Query::parse("updated:>1h").unwrap();
+        let subject = subject(None, None);
+        assert!(recent.filters_match(&subject, 1_000 + 599));
+        assert!(!recent.filters_match(&subject, 1_000 + 601));
+        assert!(!stale.filters_match(&subject, 1_000 + 3_600));
+        assert!(stale.filters_match(&subject, 1_000 + 3_601));
+        let never = Subject {
+            updated_at: None,
+            ..subject
+        };
+        assert!(stale.filters_match(&never, 0));
+        assert!(!recent.filters_match(&never, 0));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::context::{AgentStatus, ContextStore, SessionContext};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// Statuses in the order the summary lists them, most pressing first.
+const ORDER: [AgentStatus; 5] = [
+    AgentStatus::Failed,
+    AgentStatus::Waiting,
+    AgentStatus::Working,
+    AgentStatus::Idle,
+    AgentStatus::Done,
+];
+
+const SEPARATOR: &str = " · ";
+
+pub const DEFAULT_FORMAT: &str = "{summary}";
+
+/// What `jkl status-line` prints.
+#[derive(Clone, Debug)]
+pub struct StatusLineOptions {
+    /// Template with `{summary}`, `{total}` and one placeholder per status,
+    /// e.g. `{waiting}`.
+    pub format: String,
+    /// Count only this session instead of every session.
+    pub session_name: Option<String>,
+    /// Wrap summary segments in tmux `#[fg=...]` markup.
+    pub color: bool,
+}
+
+/// Number of agents in each status. A pane with a reported status counts as
+/// one agent; a session counts itself only when none of its panes reported.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct StatusCounts(HashMap<AgentStatus, usize>);
+
+impl StatusCounts {
+    fn get(&self, status: &AgentStatus) -> usize {
+        self.0.get(status).copied().unwrap_or(0)
+    }
+
+    fn add(&mut self, status: &AgentStatus) {
+        if *status != AgentStatus::None {
+            *self.0.entry(status.clone()).or_default() += 1;
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+}
+
+/// Renders the status line. `live_panes` (session name to pane ids) drops
+/// entries for sessions and panes tmux no longer has; pass `None` when tmux
+/// could not be asked.
+pub fn render(
+    store: &ContextStore,
+    live_panes: Option<&HashMap<String, HashSet<String>>>,
+    options: &StatusLineOptions,
+) -> Result<String, Box<dyn Error>> {
+    let contexts = store.load()?;
+    let entries = contexts.values().filter(|entry| {
+        let Some(name) = entry.session_name.as_deref() else {
+            return false;
+        };
+        options
+            .session_name
+            .as_deref()
+            .is_none_or(|session_name| session_name == name)
+            && live_panes.is_none_or(|live| live.contains_key(name))
+    });
+    let counts = count(entries, live_panes);
+    Ok(fill_template(&options.format, &counts, options.color))
+}
+
+fn count<'a>(
+    entries: impl Iterator<Item = &'a SessionContext>,
+    live_panes: Option<&HashMap<String, HashSet<String>>>,
+) -> StatusCounts {
+    let mut counts = StatusCounts::default();
+    for entry in entries {
+        let live = entry
+            .session_name
+            .as_deref()
+            .and_then(|name| live_panes.and_then(|live| live.get(name)));
+        let mut panes_reported = false;
+        for (pane_id, pane) in &entry.panes {
+            if live.is_some_and(|live| !live.contains(pane_id)) {
+                continue;
+            }
+            if let Some(status) = &pane.status {
+                counts.add(status);
+                panes_reported = true;
+            }
+        }
+        if !panes_reported && let Some(status) = &entry.status {
+            counts.add(status);
+        }
+    }
+    counts
+}
+
+fn fill_template(format: &str, counts: &StatusCounts, color: bool) -> String {
+    let mut output = format
+        .replace("{summary}", &summary(counts, color))
+        .replace("{total}", &counts.total().to_string());
+    for status in &ORDER {
+        output = output.replace(&format!("{{{status}}}"), &counts.get(status).to_string());
+    }
+    output
+}
+
+/// e.g. `2 waiting · 1 working`, leaving out statuses nobody is in.
+fn summary(counts: &StatusCounts, color: bool) -> String {
+    ORDER
+        .iter()
+        .filter(|status| counts.get(status) > 0)
+        .map(|status| {
+            let text = format!("{} {status}", counts.get(status));
+            if color {
+                format!("#[fg={}]{text}#[default]", tmux_color(status))
+            } else {
+                text
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(SEPARATOR)
+}
+
+/// The TUI's status palette, in tmux color names.
+fn tmux_color(status: &AgentStatus) -> &'static str {
+    match status {
+        AgentStatus::Done => "green",
+        AgentStatus::Failed => "red",
+        AgentStatus::Working => "blue",
+        AgentStatus::Waiting | AgentStatus::Idle => "yellow",
+        AgentStatus::None => "white",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: &str, session_name: Option<&str>, color: bool) -> StatusLineOptions {
+        StatusLineOptions {
+            format: format.to_string(),
+            session_name: session_name.map(str::to_string),
+            color,
+        }
+    }
+
+    #[test]
+    fn counts_live_agents_per_status() {
+        let store = ContextStore::temp();
+        store
+            .upsert_session("api".to_string(), None, Some(AgentStatus::Idle), None)
+            .unwrap();
+        store
+            .upsert_pane("api", "%1", Some(AgentStatus::Working), None)
+            .unwrap();
+        store
+            .upsert_pane("api", "%2", Some(AgentStatus::Waiting), None)
+            .unwrap();
+        store
+            .upsert_pane("api", "%9", Some(AgentStatus::Failed), None)
+            .unwrap();
+        store
+            .upsert_session("web".to_string(), None, Some(AgentStatus::Working), None)
+            .unwrap();
+        store
+            .upsert_session("gone".to_string(), None, Some(AgentStatus::Done), None)
+            .unwrap();
+        let live = HashMap::from([
+            (
+                "api".to_string(),
+                HashSet::from(["%1".to_string(), "%2".to_string()]),
+            ),
+            ("web".to_string(), HashSet::from(["%3".to_string()])),
+        ]);
+
+        let plain = render(&store, Some(&live), &options(DEFAULT_FORMAT, None, false)).unwrap();
+        assert_eq!(plain, "1 waiting · 2 working");
+        let colored = render(
+            &store,
+            Some(&live),
+            &options(DEFAULT_FORMAT, Some("api"), true),
+        )
+        .unwrap();
+        assert_eq!(
+            colored,
+            "#[fg=yellow]1 waiting#[default] · #[fg=blue]1 working#[default]"
+        );
+        let template = render(&store, None, &options("{done}/{total} done", None, false)).unwrap();
+        assert_eq!(template, "1/5 done");
+    }
+}
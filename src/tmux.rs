@@ -1,79 +1,988 @@
-use std::io;
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-#[derive(Clone, Debug)]
+pub mod control;
+mod error;
+#[cfg(test)]
+pub mod fake;
+
+pub use error::TmuxError;
+
+use control::ControlStream;
+
+#[derive(Clone, Debug, Default)]
 pub struct TmuxSession {
     pub id: String,
     pub name: String,
+    /// Terminal clients attached to the session. Control-mode clients,
+    /// including jkl's own, are not counted.
+    pub attached: u32,
+    pub windows: u32,
+    /// Unix times reported by tmux.
+    pub created: Option<u64>,
+    pub activity: Option<u64>,
+    pub last_attached: Option<u64>,
 }
 
-#[derive(Clone, Debug)]
+const SESSION_FORMAT: &str = concat!(
+    "#{session_id}\t#{session_windows}\t#{session_created}\t#{session_activity}\t",
+    "#{session_last_attached}\t#{session_attached_list}\t#{session_name}",
+);
+
+#[derive(Clone, Debug, Default)]
 pub struct TmuxPane {
+    pub session_id: String,
     pub session_name: String,
     pub pane_id: String,
+    pub window_id: String,
+    pub window_index: u32,
+    pub window_name: String,
+    pub pane_index: u32,
+    /// The active pane of its window.
+    pub active: bool,
+    /// The pane's window is the active window of its session.
+    pub window_active: bool,
+    pub pid: Option<u32>,
+    pub current_command: String,
+    pub current_path: String,
+    pub title: String,
+    /// Unix time of the last activity in the pane's window.
+    pub activity: Option<u64>,
+    /// The window rang a bell since it was last viewed.
+    pub bell: bool,
+    /// The window hit its `monitor-silence` interval.
+    pub silence: bool,
+    /// The pane's process exited (only visible with `remain-on-exit`).
+    pub dead: bool,
+    pub dead_status: Option<i32>,
+}
+
+const PANE_FORMAT: &str = concat!(
+    "#{session_id}\t#{session_name}\t#{pane_id}\t#{window_id}\t#{window_index}\t#{window_name}\t",
+    "#{pane_index}\t#{pane_active}\t#{window_active}\t#{pane_pid}\t#{window_activity}\t",
+    "#{window_bell_flag}\t#{window_silence_flag}\t#{pane_dead}\t#{pane_dead_status}\t",
+    "#{pane_current_command}\t#{pane_current_path}\t#{pane_title}",
+);
+
+/// Session fields followed by `PANE_FORMAT`, so one `list-panes -a` call
+/// describes sessions, windows and panes at the same instant.
+const SNAPSHOT_FORMAT: &str = concat!(
+    "#{session_windows}\t#{session_created}\t#{session_activity}\t#{session_last_attached}\t",
+    "#{session_attached_list}\t",
+    "#{session_id}\t#{session_name}\t#{pane_id}\t#{window_id}\t#{window_index}\t#{window_name}\t",
+    "#{pane_index}\t#{pane_active}\t#{window_active}\t#{pane_pid}\t#{window_activity}\t",
+    "#{window_bell_flag}\t#{window_silence_flag}\t#{pane_dead}\t#{pane_dead_status}\t",
+    "#{pane_current_command}\t#{pane_current_path}\t#{pane_title}",
+);
+
+/// Sessions, their windows and their panes as read in one tmux call.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    pub sessions: Vec<SessionSnapshot>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SessionSnapshot {
+    pub session: TmuxSession,
+    /// In window index order.
+    pub windows: Vec<WindowSnapshot>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WindowSnapshot {
+    pub id: String,
+    pub index: u32,
+    pub name: String,
+    pub active: bool,
+    /// In pane index order.
+    pub panes: Vec<TmuxPane>,
 }
 
-pub fn list_sessions() -> Result<Vec<TmuxSession>, io::Error> {
-    let output = Command::new("tmux")
-        .args(["list-sessions", "-F", "#{session_id}\t#{session_name}"])
-        .output()?;
-    if !output.status.success() {
-        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
-        return Err(io::Error::new(io::ErrorKind::Other, message));
+impl Snapshot {
+    /// Nests `panes` under `sessions` by session name, keeping the session
+    /// order given.
+    pub fn new(sessions: Vec<TmuxSession>, panes: Vec<TmuxPane>) -> Self {
+        let mut by_session: HashMap<String, Vec<TmuxPane>> = HashMap::new();
+        for pane in panes {
+            by_session
+                .entry(pane.session_name.clone())
+                .or_default()
+                .push(pane);
+        }
+        let sessions = sessions
+            .into_iter()
+            .map(|session| {
+                let mut panes = by_session.remove(&session.name).unwrap_or_default();
+                panes.sort_by_key(|pane| (pane.window_index, pane.pane_index));
+                let mut windows: Vec<WindowSnapshot> = Vec::new();
+                for pane in panes {
+                    match windows.last_mut() {
+                        Some(window) if window.id == pane.window_id => window.panes.push(pane),
+                        _ => windows.push(WindowSnapshot {
+                            id: pane.window_id.clone(),
+                            index: pane.window_index,
+                            name: pane.window_name.clone(),
+                            active: pane.window_active,
+                            panes: vec![pane],
+                        }),
+                    }
+                }
+                SessionSnapshot { session, windows }
+            })
+            .collect();
+        Self { sessions }
     }
-    let sessions = String::from_utf8_lossy(&output.stdout)
-        .lines()
-        .filter_map(|line| {
-            let mut parts = line.splitn(2, '\t');
-            let id = parts.next()?.trim();
-            let name = parts.next()?.trim();
-            if id.is_empty() || name.is_empty() {
-                None
-            } else {
-                Some(TmuxSession {
-                    id: id.to_string(),
-                    name: name.to_string(),
-                })
+
+    pub fn sessions(&self) -> impl Iterator<Item = &TmuxSession> {
+        self.sessions.iter().map(|entry| &entry.session)
+    }
+
+    pub fn panes(&self) -> impl Iterator<Item = &TmuxPane> {
+        self.sessions.iter().flat_map(SessionSnapshot::panes)
+    }
+
+    pub fn session(&self, session_id: &str) -> Option<&SessionSnapshot> {
+        self.sessions
+            .iter()
+            .find(|entry| entry.session.id == session_id)
+    }
+
+    /// Pane ids of each session, keyed by session name.
+    pub fn live_panes(&self) -> HashMap<String, HashSet<String>> {
+        self.sessions
+            .iter()
+            .map(|entry| {
+                let panes = entry.panes().map(|pane| pane.pane_id.clone()).collect();
+                (entry.session.name.clone(), panes)
+            })
+            .collect()
+    }
+}
+
+impl SessionSnapshot {
+    pub fn panes(&self) -> impl Iterator<Item = &TmuxPane> {
+        self.windows.iter().flat_map(|window| window.panes.iter())
+    }
+}
+
+/// A client attached to the server.
+#[derive(Clone, Debug, Default)]
+pub struct AttachedClient {
+    pub name: String,
+    pub session_name: String,
+}
+
+const CLIENT_FORMAT: &str = "#{client_control_mode}\t#{client_name}\t#{client_session}";
+
+/// A session to bring up, optionally narrowed to one of its panes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub session_id: String,
+    /// Window and pane ids.
+    pub pane: Option<(String, String)>,
+}
+
+impl Target {
+    pub fn session(session_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            pane: None,
+        }
+    }
+
+    pub fn pane(session_id: &str, window_id: &str, pane_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            pane: Some((window_id.to_string(), pane_id.to_string())),
+        }
+    }
+}
+
+/// How a target reaches the user's terminal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Handoff {
+    /// Inside tmux: move a client, the current one unless named.
+    Switch { client: Option<String> },
+    /// Outside tmux: replace jkl with `tmux attach-session`.
+    Attach,
+}
+
+impl Handoff {
+    pub fn detect(client: Option<String>) -> Self {
+        if inside_tmux() {
+            Handoff::Switch { client }
+        } else {
+            Handoff::Attach
+        }
+    }
+}
+
+/// One command of a global hook array, e.g. `session-renamed[0]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HookEntry {
+    pub name: String,
+    pub index: usize,
+    /// As tmux prints it in `show-hooks`.
+    pub command: String,
+}
+
+/// A user option (`@name`) to set on a session or pane; `None` unsets it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserOption {
+    pub scope: OptionScope,
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionScope {
+    Session(String),
+    Pane(String),
+}
+
+impl UserOption {
+    fn args(&self) -> Vec<String> {
+        let mut args = vec!["set-option".to_string()];
+        if self.value.is_none() {
+            args.push("-u".to_string());
+        }
+        let target = match &self.scope {
+            OptionScope::Session(target) => target,
+            OptionScope::Pane(target) => {
+                args.push("-p".to_string());
+                target
             }
-        })
-        .collect();
-    Ok(sessions)
+        };
+        args.extend(["-t".to_string(), target.clone(), "--".to_string()]);
+        args.push(self.name.clone());
+        if let Some(value) = &self.value {
+            args.push(escape_trailing_semicolon(value));
+        }
+        args
+    }
+}
+
+/// tmux reads an argument ending in `;` as a command separator unless the
+/// semicolon is escaped.
+fn escape_trailing_semicolon(value: &str) -> String {
+    match value.strip_suffix(';') {
+        Some(rest) => format!("{rest}\\;"),
+        None => value.to_string(),
+    }
+}
+
+/// Where tmux puts sockets for `-L` names: `${TMUX_TMPDIR:-/tmp}/tmux-$UID`.
+pub fn socket_dir() -> PathBuf {
+    let base = std::env::var_os("TMUX_TMPDIR")
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(|| PathBuf::from("/tmp"), PathBuf::from);
+    // `/proc/self` belongs to this process's user. Without procfs, as on
+    // macOS, the owner of `$HOME` stands in.
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let uid = [Some(PathBuf::from("/proc/self")), home]
+        .into_iter()
+        .flatten()
+        .find_map(|path| std::fs::metadata(path).ok())
+        .map_or(0, |metadata| metadata.uid());
+    base.join(format!("tmux-{uid}"))
+}
+
+/// The sockets in `dir`, sorted by name. Stale sockets of servers that have
+/// exited are included; talking to them fails with `TmuxError::NoServer`.
+pub fn discover_sockets(dir: &Path) -> Vec<TmuxSocket> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_socket()))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.into_iter().map(TmuxSocket::Path).collect()
+}
+
+/// Opens `target` on `server` for a client of `current`, a different server.
+/// A client cannot switch servers, so it detaches from `current` and runs
+/// `tmux attach-session` against `socket` in its place.
+pub fn open_on_server(
+    current: &dyn TmuxClient,
+    client: Option<&str>,
+    server: &dyn TmuxClient,
+    socket: &TmuxSocket,
+    target: &Target,
+) -> Result<(), TmuxError> {
+    if let Some((window_id, pane_id)) = &target.pane {
+        server.select_window(window_id)?;
+        server.select_pane(pane_id)?;
+    }
+    current.detach_client(client, &socket.attach_command(&target.session_id))
+}
+
+/// Single-quotes `word` for `sh` unless it is plainly safe.
+fn shell_quote(word: &str) -> String {
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./%@:+=".contains(c));
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+pub fn inside_tmux() -> bool {
+    std::env::var_os("TMUX").is_some_and(|value| !value.is_empty())
+}
+
+/// The tmux operations jkl relies on, so callers can run against a fake server.
+pub trait TmuxClient {
+    /// Every session, window and pane on the server, read in one call.
+    fn snapshot(&self) -> Result<Snapshot, TmuxError>;
+    /// The path of the server's socket, which tells servers apart in the
+    /// store they share.
+    fn socket_path(&self) -> Result<String, TmuxError>;
+    /// Looks up a single pane, e.g. `$TMUX_PANE`.
+    fn describe_pane(&self, target: &str) -> Result<TmuxPane, TmuxError>;
+    /// The visible contents of a pane (and some history), with color escapes.
+    fn capture_pane(&self, target: &str) -> Result<String, TmuxError>;
+    /// Terminal clients; control-mode clients are left out.
+    fn list_clients(&self) -> Result<Vec<AttachedClient>, TmuxError>;
+    /// Switches `client`, or the current client when `None`.
+    fn switch_client(&self, client: Option<&str>, target: &str) -> Result<(), TmuxError>;
+    /// Attaches this terminal to `target`. On success the current process is
+    /// replaced by tmux and this never returns.
+    fn attach_session(&self, target: &str) -> Result<(), TmuxError>;
+    /// Detaches a client, the current one unless named, and has it run
+    /// `shell_command` in place of tmux.
+    fn detach_client(&self, client: Option<&str>, shell_command: &str) -> Result<(), TmuxError>;
+    fn select_window(&self, target: &str) -> Result<(), TmuxError>;
+    fn select_pane(&self, target: &str) -> Result<(), TmuxError>;
+    /// Creates a detached session, optionally starting in `cwd` and running `command`.
+    fn new_session(
+        &self,
+        name: &str,
+        cwd: Option<&Path>,
+        command: Option<&str>,
+    ) -> Result<TmuxSession, TmuxError>;
+    fn kill_session(&self, target: &str) -> Result<(), TmuxError>;
+    fn kill_pane(&self, target: &str) -> Result<(), TmuxError>;
+    fn rename_session(&self, target: &str, name: &str) -> Result<(), TmuxError>;
+    /// Applies every option in one tmux invocation.
+    fn set_options(&self, options: &[UserOption]) -> Result<(), TmuxError>;
+    /// Global session and window hooks that have at least one command.
+    fn list_hooks(&self) -> Result<Vec<HookEntry>, TmuxError>;
+    /// Sets `name[index]` to `command`, or unsets it when `None`.
+    fn set_hook(&self, name: &str, index: usize, command: Option<&str>) -> Result<(), TmuxError>;
+    /// Opens a control-mode connection that reports server changes as they happen.
+    fn subscribe(&self) -> Result<ControlStream, TmuxError>;
+
+    /// Brings the target's window and pane to the front of its session, then
+    /// switches a client to the session or attaches to it.
+    fn open(&self, handoff: &Handoff, target: &Target) -> Result<(), TmuxError> {
+        if let Some((window_id, pane_id)) = &target.pane {
+            self.select_window(window_id)?;
+            self.select_pane(pane_id)?;
+        }
+        match handoff {
+            Handoff::Switch { client } => self.switch_client(client.as_deref(), &target.session_id),
+            Handoff::Attach => self.attach_session(&target.session_id),
+        }
+    }
+}
+
+/// Which tmux server to talk to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TmuxSocket {
+    /// Whatever plain `tmux` picks: `$TMUX` inside tmux, the default socket outside.
+    #[default]
+    Default,
+    /// `tmux -L <name>`
+    Name(String),
+    /// `tmux -S <path>`
+    Path(PathBuf),
+}
+
+impl TmuxSocket {
+    /// Parses a `JKL_TMUX_SOCKET` value: anything containing `/` is a path,
+    /// otherwise a socket name.
+    pub fn from_env_value(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            TmuxSocket::Default
+        } else if value.contains('/') {
+            TmuxSocket::Path(PathBuf::from(value))
+        } else {
+            TmuxSocket::Name(value.to_string())
+        }
+    }
+
+    /// Parses a `JKL_TMUX_SOCKETS` list; entries are separated by commas.
+    pub fn from_env_list(value: &str) -> Vec<Self> {
+        value
+            .split(',')
+            .map(Self::from_env_value)
+            .filter(|socket| *socket != TmuxSocket::Default)
+            .collect()
+    }
+
+    /// What the TUI calls the server: the socket's file name.
+    pub fn label(&self) -> String {
+        match self {
+            TmuxSocket::Default => "default".to_string(),
+            TmuxSocket::Name(name) => name.clone(),
+            TmuxSocket::Path(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+        }
+    }
+
+    /// Whether jkl runs inside a client of this server.
+    pub fn is_current(&self) -> bool {
+        let Some(TmuxSocket::Path(current)) = Self::from_tmux_env() else {
+            return false;
+        };
+        match self {
+            TmuxSocket::Default => true,
+            TmuxSocket::Name(name) => current == socket_dir().join(name),
+            TmuxSocket::Path(path) => current == *path,
+        }
+    }
+
+    /// `tmux attach-session` for this server as one shell command line.
+    pub fn attach_command(&self, session_id: &str) -> String {
+        let mut words = vec!["tmux".to_string()];
+        match self {
+            TmuxSocket::Default => {}
+            TmuxSocket::Name(name) => words.extend(["-L".to_string(), shell_quote(name)]),
+            TmuxSocket::Path(path) => {
+                words.extend(["-S".to_string(), shell_quote(&path.to_string_lossy())]);
+            }
+        }
+        words.extend([
+            "attach-session".to_string(),
+            "-t".to_string(),
+            shell_quote(session_id),
+        ]);
+        words.join(" ")
+    }
+
+    /// The socket of the server we are running inside, taken from `$TMUX`.
+    fn from_tmux_env() -> Option<Self> {
+        let tmux = std::env::var("TMUX").ok()?;
+        let path = tmux.split(',').next().filter(|path| !path.is_empty())?;
+        Some(TmuxSocket::Path(PathBuf::from(path)))
+    }
+
+    fn apply(&self, command: &mut Command) {
+        match self {
+            TmuxSocket::Default => {}
+            TmuxSocket::Name(name) => {
+                command.arg("-L").arg(name);
+            }
+            TmuxSocket::Path(path) => {
+                command.arg("-S").arg(path);
+            }
+        }
+    }
+}
+
+/// Talks to the tmux server by spawning the `tmux` binary.
+#[derive(Clone, Debug, Default)]
+pub struct SystemTmux {
+    socket: TmuxSocket,
+}
+
+impl SystemTmux {
+    pub fn new(socket: TmuxSocket) -> Self {
+        Self { socket }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("tmux");
+        // Outside tmux a non-UTF-8 locale makes tmux print the tabs in our
+        // formats as `_`; `-u` keeps them intact.
+        command.arg("-u");
+        self.socket.apply(&mut command);
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, TmuxError> {
+        let output = self.command().args(args).output()?;
+        if !output.status.success() {
+            return Err(TmuxError::from_stderr(&String::from_utf8_lossy(
+                &output.stderr,
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
 }
 
-pub fn list_panes() -> Result<Vec<TmuxPane>, io::Error> {
-    let output = Command::new("tmux")
-        .args(["list-panes", "-a", "-F", "#{session_name}\t#{pane_id}"])
-        .output()?;
-    if !output.status.success() {
-        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
-        return Err(io::Error::new(io::ErrorKind::Other, message));
+impl TmuxClient for SystemTmux {
+    fn snapshot(&self) -> Result<Snapshot, TmuxError> {
+        let output = self.run(&["list-panes", "-a", "-F", SNAPSHOT_FORMAT])?;
+        Ok(parse_snapshot(&output))
+    }
+
+    fn socket_path(&self) -> Result<String, TmuxError> {
+        let output = self.run(&["display-message", "-p", "#{socket_path}"])?;
+        Ok(output.trim_end().to_string())
+    }
+
+    fn describe_pane(&self, target: &str) -> Result<TmuxPane, TmuxError> {
+        let output = self.run(&["display-message", "-p", "-t", target, PANE_FORMAT])?;
+        output
+            .lines()
+            .next()
+            .and_then(parse_pane)
+            .ok_or_else(|| TmuxError::TargetNotFound(format!("can't find pane: {target}")))
+    }
+
+    fn capture_pane(&self, target: &str) -> Result<String, TmuxError> {
+        self.run(&["capture-pane", "-p", "-e", "-S", "-200", "-t", target])
+    }
+
+    fn list_clients(&self) -> Result<Vec<AttachedClient>, TmuxError> {
+        let output = self.run(&["list-clients", "-F", CLIENT_FORMAT])?;
+        Ok(parse_clients(&output))
+    }
+
+    fn switch_client(&self, client: Option<&str>, target: &str) -> Result<(), TmuxError> {
+        match client {
+            Some(client) => self.run(&["switch-client", "-c", client, "-t", target])?,
+            None => self.run(&["switch-client", "-t", target])?,
+        };
+        Ok(())
+    }
+
+    fn attach_session(&self, target: &str) -> Result<(), TmuxError> {
+        Err(self
+            .command()
+            .args(["attach-session", "-t", target])
+            .exec()
+            .into())
+    }
+
+    fn detach_client(&self, client: Option<&str>, shell_command: &str) -> Result<(), TmuxError> {
+        match client {
+            Some(client) => self.run(&["detach-client", "-t", client, "-E", shell_command])?,
+            None => self.run(&["detach-client", "-E", shell_command])?,
+        };
+        Ok(())
+    }
+
+    fn select_window(&self, target: &str) -> Result<(), TmuxError> {
+        self.run(&["select-window", "-t", target])?;
+        Ok(())
+    }
+
+    fn select_pane(&self, target: &str) -> Result<(), TmuxError> {
+        self.run(&["select-pane", "-t", target])?;
+        Ok(())
+    }
+
+    fn new_session(
+        &self,
+        name: &str,
+        cwd: Option<&Path>,
+        command: Option<&str>,
+    ) -> Result<TmuxSession, TmuxError> {
+        let mut args = vec!["new-session", "-d", "-P", "-F", SESSION_FORMAT, "-s", name];
+        let cwd = cwd.map(|cwd| cwd.to_string_lossy().to_string());
+        if let Some(cwd) = cwd.as_deref() {
+            args.extend(["-c", cwd]);
+        }
+        if let Some(command) = command {
+            args.push(command);
+        }
+        let output = self.run(&args)?;
+        output
+            .lines()
+            .next()
+            .and_then(parse_session)
+            .ok_or_else(|| TmuxError::Parse(format!("new-session printed {output:?}")))
+    }
+
+    fn kill_session(&self, target: &str) -> Result<(), TmuxError> {
+        self.run(&["kill-session", "-t", target])?;
+        Ok(())
     }
-    let panes = String::from_utf8_lossy(&output.stdout)
+
+    fn kill_pane(&self, target: &str) -> Result<(), TmuxError> {
+        self.run(&["kill-pane", "-t", target])?;
+        Ok(())
+    }
+
+    fn rename_session(&self, target: &str, name: &str) -> Result<(), TmuxError> {
+        self.run(&["rename-session", "-t", target, name])?;
+        Ok(())
+    }
+
+    fn set_options(&self, options: &[UserOption]) -> Result<(), TmuxError> {
+        if options.is_empty() {
+            return Ok(());
+        }
+        let commands = options.iter().map(UserOption::args).collect::<Vec<_>>();
+        let args = commands.join(&";".to_string());
+        self.run(&args.iter().map(String::as_str).collect::<Vec<_>>())?;
+        Ok(())
+    }
+
+    fn list_hooks(&self) -> Result<Vec<HookEntry>, TmuxError> {
+        // Pane hooks such as pane-exited live with the window options.
+        let mut hooks = Vec::new();
+        for scope in ["-g", "-gw"] {
+            let output = self.run(&["show-hooks", scope])?;
+            for hook in output.lines().filter_map(parse_hook) {
+                if !hooks.contains(&hook) {
+                    hooks.push(hook);
+                }
+            }
+        }
+        Ok(hooks)
+    }
+
+    fn set_hook(&self, name: &str, index: usize, command: Option<&str>) -> Result<(), TmuxError> {
+        let hook = format!("{name}[{index}]");
+        match command {
+            Some(command) => self.run(&["set-hook", "-g", &hook, command])?,
+            None => self.run(&["set-hook", "-gu", &hook])?,
+        };
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<ControlStream, TmuxError> {
+        let mut command = Command::new("tmux");
+        command.arg("-u");
+        // `$TMUX` is removed below, so pin the server it pointed at first.
+        match &self.socket {
+            TmuxSocket::Default => TmuxSocket::from_tmux_env()
+                .unwrap_or_default()
+                .apply(&mut command),
+            socket => socket.apply(&mut command),
+        }
+        // A read-only client that never receives pane output or resizes windows.
+        command
+            .args([
+                "-C",
+                "attach-session",
+                "-f",
+                "no-output,read-only,ignore-size",
+            ])
+            .env_remove("TMUX");
+        Ok(ControlStream::spawn(command)?)
+    }
+}
+
+/// Parses a `show-hooks` line such as `pane-exited[2] run-shell "..."`.
+/// Hooks without commands are printed as a bare name and yield `None`.
+pub fn parse_hook(line: &str) -> Option<HookEntry> {
+    let (hook, command) = line.split_once(' ')?;
+    let (name, index) = hook.strip_suffix(']')?.split_once('[')?;
+    Some(HookEntry {
+        name: name.to_string(),
+        index: index.parse().ok()?,
+        command: command.to_string(),
+    })
+}
+
+fn parse_session(line: &str) -> Option<TmuxSession> {
+    // The name comes last so a tab inside it stays part of the name.
+    let fields = line.splitn(7, '\t').collect::<Vec<_>>();
+    let [id, windows, created, activity, last_attached, clients, name] = fields.as_slice() else {
+        return None;
+    };
+    let id = id.trim();
+    let name = name.trim();
+    if id.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some(session_from_fields(
+        id,
+        name,
+        [windows, created, activity, last_attached, clients],
+    ))
+}
+
+/// Builds a session from `session_windows`, `session_created`,
+/// `session_activity`, `session_last_attached` and `session_attached_list`.
+fn session_from_fields(id: &str, name: &str, fields: [&str; 5]) -> TmuxSession {
+    let [windows, created, activity, last_attached, clients] = fields;
+    let attached = clients
+        .split(',')
+        .filter(|client| !client.is_empty() && !client.starts_with("client-"))
+        .count();
+    TmuxSession {
+        id: id.to_string(),
+        name: name.to_string(),
+        attached: u32::try_from(attached).unwrap_or(u32::MAX),
+        windows: windows.parse().unwrap_or_default(),
+        created: created.parse().ok(),
+        activity: activity.parse().ok(),
+        last_attached: last_attached.parse().ok(),
+    }
+}
+
+fn parse_snapshot(output: &str) -> Snapshot {
+    let mut sessions: Vec<TmuxSession> = Vec::new();
+    let mut panes = Vec::new();
+    for line in output.lines() {
+        let fields = line.splitn(6, '\t').collect::<Vec<_>>();
+        let [windows, created, activity, last_attached, clients, rest] = fields.as_slice() else {
+            continue;
+        };
+        let Some(pane) = parse_pane(rest) else {
+            continue;
+        };
+        if sessions
+            .last()
+            .is_none_or(|session| session.id != pane.session_id)
+        {
+            sessions.push(session_from_fields(
+                &pane.session_id,
+                &pane.session_name,
+                [windows, created, activity, last_attached, clients],
+            ));
+        }
+        panes.push(pane);
+    }
+    Snapshot::new(sessions, panes)
+}
+
+fn parse_clients(output: &str) -> Vec<AttachedClient> {
+    output
         .lines()
         .filter_map(|line| {
-            let mut parts = line.splitn(2, '\t');
-            let session_name = parts.next()?.trim();
-            let pane_id = parts.next()?.trim();
-            if session_name.is_empty() || pane_id.is_empty() {
+            let mut parts = line.splitn(3, '\t');
+            let control_mode = parts.next()?;
+            let name = parts.next()?.trim();
+            let session_name = parts.next().unwrap_or_default();
+            if control_mode == "1" || name.is_empty() {
                 None
             } else {
-                Some(TmuxPane {
+                Some(AttachedClient {
+                    name: name.to_string(),
                     session_name: session_name.to_string(),
-                    pane_id: pane_id.to_string(),
                 })
             }
         })
-        .collect();
-    Ok(panes)
+        .collect()
 }
 
-pub fn switch_client(target: &str) -> Result<(), io::Error> {
-    let output = Command::new("tmux")
-        .args(["switch-client", "-t", target])
-        .output()?;
-    if !output.status.success() {
-        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
-        return Err(io::Error::new(io::ErrorKind::Other, message));
+fn parse_pane(line: &str) -> Option<TmuxPane> {
+    // The title comes last so a tab inside it stays part of the title.
+    let fields = line.splitn(18, '\t').collect::<Vec<_>>();
+    let [
+        session_id,
+        session_name,
+        pane_id,
+        window_id,
+        window_index,
+        window_name,
+        pane_index,
+        active,
+        window_active,
+        pid,
+        activity,
+        bell,
+        silence,
+        dead,
+        dead_status,
+        current_command,
+        current_path,
+        title,
+    ] = fields.as_slice()
+    else {
+        return None;
+    };
+    let session_name = session_name.trim();
+    let pane_id = pane_id.trim();
+    if session_name.is_empty() || pane_id.is_empty() {
+        return None;
+    }
+    Some(TmuxPane {
+        session_id: session_id.to_string(),
+        session_name: session_name.to_string(),
+        pane_id: pane_id.to_string(),
+        window_id: window_id.to_string(),
+        window_index: window_index.parse().unwrap_or_default(),
+        window_name: window_name.to_string(),
+        pane_index: pane_index.parse().unwrap_or_default(),
+        active: *active == "1",
+        window_active: *window_active == "1",
+        pid: pid.parse().ok(),
+        current_command: current_command.to_string(),
+        current_path: current_path.to_string(),
+        title: title.to_string(),
+        activity: activity.parse().ok(),
+        bell: *bell == "1",
+        silence: *silence == "1",
+        dead: *dead == "1",
+        dead_status: dead_status.parse().ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn socket_selection_adds_server_flags() {
+        let args = |socket: TmuxSocket| {
+            let tmux = SystemTmux::new(socket);
+            let command = tmux.command();
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(args(TmuxSocket::Default), ["-u"]);
+        assert_eq!(
+            args(TmuxSocket::from_env_value("test")),
+            ["-u", "-L", "test"]
+        );
+        assert_eq!(
+            args(TmuxSocket::from_env_value("/tmp/tmux-1000/work")),
+            ["-u", "-S", "/tmp/tmux-1000/work"]
+        );
+        assert_eq!(TmuxSocket::from_env_value("  "), TmuxSocket::Default);
+    }
+
+    #[test]
+    fn user_options_build_set_option_commands() {
+        let set = UserOption {
+            scope: OptionScope::Pane("%1".to_string()),
+            name: "@jkl_context".to_string(),
+            value: Some("retry;".to_string()),
+        };
+        assert_eq!(
+            set.args(),
+            [
+                "set-option",
+                "-p",
+                "-t",
+                "%1",
+                "--",
+                "@jkl_context",
+                "retry\\;"
+            ]
+        );
+        let unset = UserOption {
+            scope: OptionScope::Session("=api:".to_string()),
+            name: "@jkl_status".to_string(),
+            value: None,
+        };
+        assert_eq!(
+            unset.args(),
+            ["set-option", "-u", "-t", "=api:", "--", "@jkl_status"]
+        );
+    }
+
+    #[test]
+    fn parses_session_details() {
+        let output = "$1\t3\t1700000000\t1700000500\t1700000400\t/dev/pts/1,client-99,/dev/pts/4\tmy work\n\
+                      $2\t1\t1700000000\t1700000000\t\t\tidle\n";
+        let sessions = output.lines().filter_map(parse_session).collect::<Vec<_>>();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].name, "my work");
+        assert_eq!(sessions[0].attached, 2);
+        assert_eq!(sessions[0].windows, 3);
+        assert_eq!(sessions[0].activity, Some(1_700_000_500));
+        assert_eq!(sessions[1].attached, 0);
+        assert_eq!(sessions[1].last_attached, None);
+    }
+
+    #[test]
+    fn parses_pane_details() {
+        let output = "2\t1700000000\t1700000600\t\t\t$1\twork\t%3\t@2\t1\teditor\t0\t1\t0\t4242\t1700000000\t1\t0\t0\t\tnvim\t/home/me/api\tagent\tone\n\
+                      broken line\n";
+        let panes = parse_snapshot(output).panes().cloned().collect::<Vec<_>>();
+        assert_eq!(panes.len(), 1);
+        let pane = &panes[0];
+        assert_eq!(pane.session_name, "work");
+        assert_eq!(pane.pane_id, "%3");
+        assert_eq!(pane.window_id, "@2");
+        assert_eq!(pane.window_index, 1);
+        assert_eq!(pane.window_name, "editor");
+        assert!(pane.active);
+        assert!(!pane.window_active);
+        assert_eq!(pane.pid, Some(4242));
+        assert_eq!(pane.activity, Some(1_700_000_000));
+        assert!(pane.bell);
+        assert!(!pane.dead);
+        assert_eq!(pane.dead_status, None);
+        assert_eq!(pane.current_command, "nvim");
+        assert_eq!(pane.current_path, "/home/me/api");
+        assert_eq!(pane.title, "agent\tone");
+    }
+
+    #[test]
+    fn snapshot_nests_panes_under_windows_and_sessions() {
+        let row = |session: &str,
+                   name: &str,
+                   pane: &str,
+                   window: &str,
+                   index: u32,
+                   pane_index: u32| {
+            format!(
+                "2\t1700000000\t1700000600\t\t/dev/pts/1\t{session}\t{name}\t{pane}\t{window}\t{index}\tw{index}\t{pane_index}\t1\t1\t1\t\t0\t0\t0\t\tsh\t/\t"
+            )
+        };
+        let output = [
+            row("$1", "api", "%1", "@1", 0, 0),
+            row("$1", "api", "%2", "@1", 0, 1),
+            row("$1", "api", "%3", "@2", 1, 0),
+            row("$2", "web", "%4", "@3", 0, 0),
+        ]
+        .join("\n");
+        let snapshot = parse_snapshot(&output);
+        let names = snapshot
+            .sessions()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["api", "web"]);
+        let api = snapshot.session("$1").unwrap();
+        assert_eq!(api.session.attached, 1);
+        assert_eq!(api.session.activity, Some(1_700_000_600));
+        assert_eq!(api.windows.len(), 2);
+        assert_eq!(api.windows[0].name, "w0");
+        assert_eq!(api.windows[0].panes.len(), 2);
+        assert_eq!(api.windows[1].panes[0].pane_id, "%3");
+        let live = snapshot.live_panes();
+        assert_eq!(live["web"], HashSet::from(["%4".to_string()]));
+    }
+
+    #[test]
+    fn discovers_sockets_and_builds_attach_commands() {
+        let dir = std::env::temp_dir().join(format!("jkl-sockets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let _b = std::os::unix::net::UnixListener::bind(dir.join("clientB")).unwrap();
+        let _a = std::os::unix::net::UnixListener::bind(dir.join("clientA")).unwrap();
+        std::fs::write(dir.join("notes"), "").unwrap();
+        let sockets = discover_sockets(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let labels = sockets.iter().map(TmuxSocket::label).collect::<Vec<_>>();
+        assert_eq!(labels, ["clientA", "clientB"]);
+
+        assert_eq!(
+            TmuxSocket::from_env_list("clientA, /run/tmux/it's,"),
+            [
+                TmuxSocket::Name("clientA".to_string()),
+                TmuxSocket::Path(PathBuf::from("/run/tmux/it's")),
+            ]
+        );
+        assert_eq!(
+            TmuxSocket::Path(PathBuf::from("/run/tmux/it's")).attach_command("$3"),
+            "tmux -S '/run/tmux/it'\\''s' attach-session -t '$3'"
+        );
+        assert_eq!(
+            TmuxSocket::Name("clientA".to_string()).attach_command("$3"),
+            "tmux -L clientA attach-session -t '$3'"
+        );
     }
-    Ok(())
 }
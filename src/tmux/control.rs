@@ -0,0 +1,206 @@
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// A notification pushed by a tmux control-mode client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlEvent {
+    SessionsChanged,
+    SessionChanged {
+        session_id: String,
+        name: String,
+    },
+    SessionRenamed {
+        session_id: String,
+        name: String,
+    },
+    SessionWindowChanged {
+        session_id: String,
+        window_id: String,
+    },
+    WindowAdd {
+        window_id: String,
+    },
+    WindowClose {
+        window_id: String,
+    },
+    WindowRenamed {
+        window_id: String,
+        name: String,
+    },
+    WindowPaneChanged {
+        window_id: String,
+        pane_id: String,
+    },
+    LayoutChange {
+        window_id: String,
+    },
+    PaneModeChanged {
+        pane_id: String,
+    },
+    /// The control client went away; no further events will arrive.
+    Exit,
+}
+
+/// A long-lived `tmux -C` connection delivering notifications on a channel.
+///
+/// The child process is killed when the stream is dropped.
+pub struct ControlStream {
+    child: Option<Child>,
+    events: Receiver<ControlEvent>,
+}
+
+impl ControlStream {
+    /// Spawns `command` (a `tmux -C ...` invocation) and reads its
+    /// notifications on a background thread.
+    pub fn spawn(mut command: Command) -> Result<Self, io::Error> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("tmux control client has no stdout"))?;
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            let mut in_reply = false;
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                // Command replies are wrapped in %begin ... %end/%error guards.
+                if line.starts_with("%begin ") {
+                    in_reply = true;
+                    continue;
+                }
+                if in_reply {
+                    if line.starts_with("%end ") || line.starts_with("%error ") {
+                        in_reply = false;
+                    }
+                    continue;
+                }
+                if let Some(event) = parse_notification(&line)
+                    && sender.send(event).is_err()
+                {
+                    return;
+                }
+            }
+            let _ = sender.send(ControlEvent::Exit);
+        });
+        Ok(Self {
+            child: Some(child),
+            events,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn from_receiver(events: Receiver<ControlEvent>) -> Self {
+        Self {
+            child: None,
+            events,
+        }
+    }
+
+    /// Returns every event received so far without blocking. A closed
+    /// channel is reported as a trailing `ControlEvent::Exit`.
+    pub fn drain(&self) -> Vec<ControlEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if events.last() != Some(&ControlEvent::Exit) {
+                        events.push(ControlEvent::Exit);
+                    }
+                    break;
+                }
+            }
+        }
+        events
+    }
+}
+
+impl Drop for ControlStream {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+pub fn parse_notification(line: &str) -> Option<ControlEvent> {
+    let mut parts = line.splitn(3, ' ');
+    let kind = parts.next()?;
+    let first = parts.next().unwrap_or_default().to_string();
+    let rest = parts.next().unwrap_or_default().to_string();
+    let event = match kind {
+        "%sessions-changed" => ControlEvent::SessionsChanged,
+        "%session-changed" => ControlEvent::SessionChanged {
+            session_id: first,
+            name: rest,
+        },
+        "%session-renamed" => ControlEvent::SessionRenamed {
+            session_id: first,
+            name: rest,
+        },
+        "%session-window-changed" => ControlEvent::SessionWindowChanged {
+            session_id: first,
+            window_id: rest,
+        },
+        "%window-add" | "%unlinked-window-add" => ControlEvent::WindowAdd { window_id: first },
+        "%window-close" | "%unlinked-window-close" => {
+            ControlEvent::WindowClose { window_id: first }
+        }
+        "%window-renamed" | "%unlinked-window-renamed" => ControlEvent::WindowRenamed {
+            window_id: first,
+            name: rest,
+        },
+        "%window-pane-changed" => ControlEvent::WindowPaneChanged {
+            window_id: first,
+            pane_id: rest,
+        },
+        "%layout-change" => ControlEvent::LayoutChange { window_id: first },
+        "%pane-mode-changed" => ControlEvent::PaneModeChanged { pane_id: first },
+        "%exit" => ControlEvent::Exit,
+        _ => return None,
+    };
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_session_notifications() {
+        assert_eq!(
+            parse_notification("%session-renamed $2 my project"),
+            Some(ControlEvent::SessionRenamed {
+                session_id: "$2".to_string(),
+                name: "my project".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_notification("%sessions-changed"),
+            Some(ControlEvent::SessionsChanged)
+        );
+        assert_eq!(
+            parse_notification("%layout-change @3 41a3,80x24,0,0 41a3,80x24,0,0 *"),
+            Some(ControlEvent::LayoutChange {
+                window_id: "@3".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_notification("%pane-mode-changed %4"),
+            Some(ControlEvent::PaneModeChanged {
+                pane_id: "%4".to_string(),
+            })
+        );
+        assert_eq!(parse_notification("%output %1 hello"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::io;
+
+/// Why a tmux command failed, sorted into the cases callers handle
+/// differently.
+#[derive(Debug)]
+pub enum TmuxError {
+    /// The `tmux` binary could not be started.
+    NotInstalled,
+    /// No server is listening on the socket.
+    NoServer(String),
+    /// The command needs a client and none was given or attached.
+    NoClient(String),
+    /// A session, window, pane or client named in the command is gone.
+    TargetNotFound(String),
+    /// tmux succeeded but printed something jkl could not read.
+    Parse(String),
+    /// Any other failure reported by tmux.
+    Command(String),
+    Io(io::Error),
+}
+
+impl TmuxError {
+    /// Sorts a failed command by its stderr.
+    pub fn from_stderr(message: &str) -> Self {
+        let message = message.trim().to_string();
+        let lower = message.to_lowercase();
+        if lower.starts_with("no server running") || lower.starts_with("error connecting to") {
+            TmuxError::NoServer(message)
+        } else if lower.starts_with("no current client") || lower.starts_with("can't find client") {
+            TmuxError::NoClient(message)
+        } else if lower.starts_with("can't find ") || lower.starts_with("no such session") {
+            TmuxError::TargetNotFound(message)
+        } else {
+            TmuxError::Command(message)
+        }
+    }
+
+    /// True when there is no tmux to talk to at all, as opposed to a single
+    /// command failing.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self, TmuxError::NotInstalled | TmuxError::NoServer(_))
+    }
+
+    /// The process exit status `jkl` uses for this error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            TmuxError::NotInstalled => 3,
+            TmuxError::NoServer(_) => 4,
+            TmuxError::NoClient(_) => 5,
+            TmuxError::TargetNotFound(_) => 6,
+            TmuxError::Parse(_) => 7,
+            TmuxError::Command(_) | TmuxError::Io(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for TmuxError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TmuxError::NotInstalled => formatter.write_str("tmux is not installed or not on PATH"),
+            TmuxError::NoServer(message) => write!(formatter, "no tmux server running ({message})"),
+            TmuxError::NoClient(message)
+            | TmuxError::TargetNotFound(message)
+            | TmuxError::Command(message) => formatter.write_str(message),
+            TmuxError::Parse(message) => write!(formatter, "unexpected tmux output: {message}"),
+            TmuxError::Io(error) => error.fmt(formatter),
+        }
+    }
+}
+
+impl std::error::Error for TmuxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TmuxError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TmuxError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::NotFound {
+            TmuxError::NotInstalled
+        } else {
+            TmuxError::Io(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_tmux_stderr() {
+        let kind = |message| TmuxError::from_stderr(message).exit_code();
+        assert_eq!(
+            kind("error connecting to /tmp/tmux-0/default (No such file or directory)\n"),
+            4
+        );
+        assert_eq!(kind("no server running on /tmp/tmux-0/default"), 4);
+        assert_eq!(kind("no current client"), 5);
+        assert_eq!(kind("can't find client: /dev/pts/9"), 5);
+        assert_eq!(kind("can't find session: api"), 6);
+        assert_eq!(kind("no such session: =api:"), 6);
+        assert_eq!(kind("duplicate session: api"), 1);
+        let missing = io::Error::new(io::ErrorKind::NotFound, "No such file or directory");
+        assert!(TmuxError::from(missing).is_unavailable());
+    }
+}
@@ -0,0 +1,396 @@
+use super::control::{ControlEvent, ControlStream};
+use super::{
+    AttachedClient, HookEntry, OptionScope, Snapshot, TmuxClient, TmuxError, TmuxPane, TmuxSession,
+    UserOption,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+
+/// In-memory tmux server for tests. Mutations go through `&self` so a test can
+/// keep a reference while the code under test holds another.
+#[derive(Debug, Default)]
+pub struct FakeTmux {
+    sessions: RefCell<Vec<TmuxSession>>,
+    panes: RefCell<Vec<TmuxPane>>,
+    clients: RefCell<Vec<AttachedClient>>,
+    calls: RefCell<Vec<String>>,
+    output: RefCell<HashMap<String, String>>,
+    /// User options keyed by target and option name.
+    options: RefCell<HashMap<(String, String), String>>,
+    hooks: RefCell<Vec<HookEntry>>,
+    /// Set by `stop_server`; cleared again by `new_session`, like tmux.
+    stopped: Cell<bool>,
+    /// What `socket_path` reports; `/tmp/tmux-0/default` when empty.
+    socket: String,
+    subscriber: RefCell<Option<Sender<ControlEvent>>>,
+}
+
+impl FakeTmux {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A server listening on `socket`, to tell several apart.
+    pub fn on_socket(socket: &str) -> Self {
+        Self {
+            socket: socket.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn add_session(&self, id: &str, name: &str) {
+        self.push_session(TmuxSession {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..TmuxSession::default()
+        });
+    }
+
+    pub fn push_session(&self, session: TmuxSession) {
+        self.sessions.borrow_mut().push(session);
+    }
+
+    /// Adds a pane in a window of its own.
+    pub fn add_pane(&self, session_name: &str, pane_id: &str) {
+        let session_id = self
+            .sessions
+            .borrow()
+            .iter()
+            .find(|session| session.name == session_name)
+            .map(|session| session.id.clone())
+            .unwrap_or_default();
+        self.push_pane(TmuxPane {
+            session_id,
+            session_name: session_name.to_string(),
+            pane_id: pane_id.to_string(),
+            window_id: format!("@{}", pane_id.trim_start_matches('%')),
+            ..TmuxPane::default()
+        });
+    }
+
+    pub fn push_pane(&self, pane: TmuxPane) {
+        self.panes.borrow_mut().push(pane);
+    }
+
+    pub fn remove_pane(&self, pane_id: &str) {
+        self.panes
+            .borrow_mut()
+            .retain(|pane| pane.pane_id != pane_id);
+    }
+
+    pub fn rename_session(&self, id: &str, name: &str) {
+        for session in self.sessions.borrow_mut().iter_mut() {
+            if session.id == id {
+                let old_name = std::mem::replace(&mut session.name, name.to_string());
+                for pane in self.panes.borrow_mut().iter_mut() {
+                    if pane.session_name == old_name {
+                        pane.session_name = name.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Makes listing fail as if no server were running, until a session is
+    /// created.
+    pub fn stop_server(&self) {
+        self.stopped.set(true);
+        self.sessions.borrow_mut().clear();
+        self.panes.borrow_mut().clear();
+    }
+
+    fn running(&self) -> Result<(), TmuxError> {
+        if self.stopped.get() {
+            return Err(TmuxError::NoServer(
+                "error connecting to /tmp/tmux-0/default".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn add_client(&self, name: &str, session_name: &str) {
+        self.clients.borrow_mut().push(AttachedClient {
+            name: name.to_string(),
+            session_name: session_name.to_string(),
+        });
+    }
+
+    /// Sets what `capture_pane` returns for `target` (a pane or session id).
+    pub fn set_output(&self, target: &str, output: &str) {
+        self.output
+            .borrow_mut()
+            .insert(target.to_string(), output.to_string());
+    }
+
+    /// Delivers `event` to the most recent `subscribe` stream, if any.
+    pub fn emit(&self, event: ControlEvent) {
+        if let Some(sender) = self.subscriber.borrow().as_ref() {
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Targets passed to `switch_client`, oldest first.
+    pub fn switched(&self) -> Vec<String> {
+        self.calls()
+            .iter()
+            .filter_map(|call| call.strip_prefix("switch-client "))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The value of user option `name` on a session or pane target.
+    pub fn option(&self, target: &str, name: &str) -> Option<String> {
+        self.options
+            .borrow()
+            .get(&(target.to_string(), name.to_string()))
+            .cloned()
+    }
+
+    /// Every state-changing command except option changes, oldest first, as
+    /// `"<command> <target>"`.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.borrow().clone()
+    }
+
+    fn has_session(&self, target: &str) -> bool {
+        self.sessions
+            .borrow()
+            .iter()
+            .any(|session| session.id == target || session.name == target)
+    }
+
+    fn record(&self, command: &str, target: &str, exists: bool) -> Result<(), TmuxError> {
+        if !exists {
+            return Err(TmuxError::TargetNotFound(format!("can't find {target}")));
+        }
+        self.calls.borrow_mut().push(format!("{command} {target}"));
+        Ok(())
+    }
+}
+
+impl TmuxClient for FakeTmux {
+    fn socket_path(&self) -> Result<String, TmuxError> {
+        self.running()?;
+        if self.socket.is_empty() {
+            return Ok("/tmp/tmux-0/default".to_string());
+        }
+        Ok(self.socket.clone())
+    }
+
+    fn snapshot(&self) -> Result<Snapshot, TmuxError> {
+        self.running()?;
+        Ok(Snapshot::new(
+            self.sessions.borrow().clone(),
+            self.panes.borrow().clone(),
+        ))
+    }
+
+    fn describe_pane(&self, target: &str) -> Result<TmuxPane, TmuxError> {
+        self.panes
+            .borrow()
+            .iter()
+            .find(|pane| pane.pane_id == target)
+            .cloned()
+            .ok_or_else(|| TmuxError::TargetNotFound(format!("can't find pane: {target}")))
+    }
+
+    fn capture_pane(&self, target: &str) -> Result<String, TmuxError> {
+        self.output
+            .borrow()
+            .get(target)
+            .cloned()
+            .ok_or_else(|| TmuxError::TargetNotFound(format!("can't find pane: {target}")))
+    }
+
+    fn list_clients(&self) -> Result<Vec<AttachedClient>, TmuxError> {
+        Ok(self.clients.borrow().clone())
+    }
+
+    fn switch_client(&self, client: Option<&str>, target: &str) -> Result<(), TmuxError> {
+        let exists = self.has_session(target);
+        match client {
+            Some(client) => self.record(&format!("switch-client -c {client}"), target, exists),
+            None => self.record("switch-client", target, exists),
+        }
+    }
+
+    fn attach_session(&self, target: &str) -> Result<(), TmuxError> {
+        let exists = self.has_session(target);
+        self.record("attach-session", target, exists)
+    }
+
+    fn detach_client(&self, client: Option<&str>, shell_command: &str) -> Result<(), TmuxError> {
+        let command = match client {
+            Some(client) => format!("detach-client -t {client} -E"),
+            None => "detach-client -E".to_string(),
+        };
+        self.record(&command, shell_command, true)
+    }
+
+    fn select_window(&self, target: &str) -> Result<(), TmuxError> {
+        let exists = self
+            .panes
+            .borrow()
+            .iter()
+            .any(|pane| pane.window_id == target);
+        self.record("select-window", target, exists)
+    }
+
+    fn select_pane(&self, target: &str) -> Result<(), TmuxError> {
+        let exists = self
+            .panes
+            .borrow()
+            .iter()
+            .any(|pane| pane.pane_id == target);
+        self.record("select-pane", target, exists)
+    }
+
+    fn new_session(
+        &self,
+        name: &str,
+        cwd: Option<&Path>,
+        command: Option<&str>,
+    ) -> Result<TmuxSession, TmuxError> {
+        self.stopped.set(false);
+        if name.is_empty() || self.has_session(name) {
+            return Err(TmuxError::Command(format!("duplicate session: {name}")));
+        }
+        let number = 100 + self.sessions.borrow().len();
+        let session = TmuxSession {
+            id: format!("${number}"),
+            name: name.to_string(),
+            windows: 1,
+            ..TmuxSession::default()
+        };
+        self.push_session(session.clone());
+        self.push_pane(TmuxPane {
+            session_id: session.id.clone(),
+            session_name: name.to_string(),
+            pane_id: format!("%{number}"),
+            window_id: format!("@{number}"),
+            current_path: cwd.map(|cwd| cwd.display().to_string()).unwrap_or_default(),
+            current_command: command.unwrap_or_default().to_string(),
+            ..TmuxPane::default()
+        });
+        self.calls.borrow_mut().push(format!("new-session {name}"));
+        Ok(session)
+    }
+
+    fn kill_session(&self, target: &str) -> Result<(), TmuxError> {
+        let exists = self.has_session(target);
+        self.record("kill-session", target, exists)?;
+        let mut sessions = self.sessions.borrow_mut();
+        let removed = sessions
+            .iter()
+            .filter(|session| session.id == target || session.name == target)
+            .map(|session| session.name.clone())
+            .collect::<Vec<_>>();
+        sessions.retain(|session| !removed.contains(&session.name));
+        self.panes
+            .borrow_mut()
+            .retain(|pane| !removed.contains(&pane.session_name));
+        Ok(())
+    }
+
+    /// Replaces `.` and `:` in `name` with `_`, as tmux does.
+    fn rename_session(&self, target: &str, name: &str) -> Result<(), TmuxError> {
+        let name = &name.replace(['.', ':'], "_");
+        if self.has_session(name) {
+            return Err(TmuxError::Command(format!("duplicate session: {name}")));
+        }
+        self.record("rename-session", target, self.has_session(target))?;
+        let session_id = self
+            .sessions
+            .borrow()
+            .iter()
+            .find(|session| session.id == target || session.name == target)
+            .map(|session| session.id.clone())
+            .unwrap_or_default();
+        FakeTmux::rename_session(self, &session_id, name);
+        Ok(())
+    }
+
+    fn list_hooks(&self) -> Result<Vec<HookEntry>, TmuxError> {
+        let mut hooks = self.hooks.borrow().clone();
+        hooks.sort_by(|a, b| (&a.name, a.index).cmp(&(&b.name, b.index)));
+        Ok(hooks)
+    }
+
+    /// Stores `command` verbatim; tests compare against what was set rather
+    /// than tmux's requoted form.
+    fn set_hook(&self, name: &str, index: usize, command: Option<&str>) -> Result<(), TmuxError> {
+        let mut hooks = self.hooks.borrow_mut();
+        hooks.retain(|hook| !(hook.name == name && hook.index == index));
+        if let Some(command) = command {
+            hooks.push(HookEntry {
+                name: name.to_string(),
+                index,
+                command: command.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn set_options(&self, options: &[UserOption]) -> Result<(), TmuxError> {
+        for option in options {
+            let (target, exists) = match &option.scope {
+                OptionScope::Session(target) => {
+                    let name = target.trim_start_matches('=').trim_end_matches(':');
+                    (target, self.has_session(name))
+                }
+                OptionScope::Pane(target) => (
+                    target,
+                    self.panes
+                        .borrow()
+                        .iter()
+                        .any(|pane| &pane.pane_id == target),
+                ),
+            };
+            if !exists {
+                return Err(TmuxError::TargetNotFound(format!("can't find {target}")));
+            }
+            let key = (target.clone(), option.name.clone());
+            match &option.value {
+                Some(value) => self.options.borrow_mut().insert(key, value.clone()),
+                None => self.options.borrow_mut().remove(&key),
+            };
+        }
+        Ok(())
+    }
+
+    /// Like tmux, killing the last pane of a session also closes the session.
+    fn kill_pane(&self, target: &str) -> Result<(), TmuxError> {
+        let session_name = self
+            .panes
+            .borrow()
+            .iter()
+            .find(|pane| pane.pane_id == target)
+            .map(|pane| pane.session_name.clone());
+        self.record("kill-pane", target, session_name.is_some())?;
+        self.remove_pane(target);
+        if let Some(session_name) = session_name
+            && !self
+                .panes
+                .borrow()
+                .iter()
+                .any(|pane| pane.session_name == session_name)
+        {
+            self.sessions
+                .borrow_mut()
+                .retain(|session| session.name != session_name);
+            // Like tmux, the server exits with its last session.
+            if self.sessions.borrow().is_empty() {
+                self.stop_server();
+            }
+        }
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<ControlStream, TmuxError> {
+        let (sender, receiver) = mpsc::channel();
+        *self.subscriber.borrow_mut() = Some(sender);
+        Ok(ControlStream::from_receiver(receiver))
+    }
+}
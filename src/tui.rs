@@ -1,4 +1,13 @@
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crate::context::{ContextStore, unix_now};
+use crate::tmux::control::{ControlEvent, ControlStream};
+use crate::tmux::{
+    AttachedClient, Handoff, SessionSnapshot, Snapshot, SystemTmux, Target, TmuxClient, TmuxError,
+    TmuxSocket,
+};
+use ratatui::crossterm::event::{
+    self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers,
+};
 use ratatui::layout::{Alignment, Constraint, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span, Text};
@@ -7,96 +16,654 @@ use ratatui::{DefaultTerminal, Frame};
 use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
 use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 use unicode_width::UnicodeWidthStr;
 
+mod ansi;
+mod board;
+mod form;
+
+use crate::actions::KillTarget;
+use board::Board;
+use form::{ConfirmDialog, FormAction, NewSessionForm, PromptDialog, StatusDialog};
+
 const DATA_NOT_RECEIVED: &str = "-";
-const INFO_TEXT: &str = "(Esc/Ctrl+C) back/quit | (/) search | (Enter) switch | (↑/↓) move | (l/h) expand/collapse | (r) refresh";
-
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let sessions = crate::tmux::list_sessions()?;
-    let contexts = crate::context::load_contexts()?;
-    let panes = crate::tmux::list_panes()?;
-    let items = build_sessions(sessions, contexts, panes);
-    let mut app = App::new(items)?;
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const PREVIEW_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const PREVIEW_PAGE: u16 = 10;
+/// How long to wait before reconnecting a control client that went away,
+/// doubling on each failure up to the maximum.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+const BOARD_INFO_TEXT: &str = "(Esc/Ctrl+C) back/quit | (v) table | (/) search | (Enter) switch | (h/j/k/l) move | (H/L) move card | (s/S) status | (e) context | (r) refresh | (o/O) sort/reverse | (a) attached only | (i) inferred status | (c) client | (n) new";
+const INFO_TEXT: &str = "(Esc/Ctrl+C) back/quit | (/) search | (Enter) switch | (↑/↓) move | (l/h) expand/collapse | (r) refresh | (o/O) sort/reverse | (a) attached only | (p) preview (J/K scroll) | (i) inferred status | (c) client | (n) new | (R) rename | (Space) mark | (x) kill | (s/S) status | (e) context | (v) board";
+
+/// Startup settings for the main TUI.
+#[derive(Clone, Debug, Default)]
+pub struct TuiOptions {
+    /// Suggest statuses from tmux signals where agents have not reported one.
+    pub infer: bool,
+    /// Client to switch instead of the current one when inside tmux.
+    pub client: Option<String>,
+    /// Servers to list sessions from, grouped by server; empty lists only
+    /// the server `tmux` talks to.
+    pub servers: Vec<TmuxSocket>,
+    pub search: SearchBackend,
+}
+
+/// What filters sessions as the search query changes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchBackend {
+    /// `crate::fuzzy`, in process.
+    #[default]
+    Builtin,
+    /// `fzf --filter`, spawned per keystroke; the built-in matcher takes
+    /// over when fzf is missing or fails.
+    Fzf,
+}
+
+pub fn run(
+    tmux: &dyn TmuxClient,
+    store: ContextStore,
+    options: TuiOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let clients = options
+        .servers
+        .iter()
+        .map(|socket| SystemTmux::new(socket.clone()))
+        .collect::<Vec<_>>();
+    let mut app = if clients.is_empty() {
+        App::load(tmux, store)?
+    } else {
+        let servers = options
+            .servers
+            .iter()
+            .zip(&clients)
+            .map(|(socket, client)| Server::new(socket.clone(), client))
+            .collect();
+        App::load_servers(tmux, servers, store)?
+    };
+    app.infer = options.infer;
+    app.search_backend = options.search;
+    app.handoff = Handoff::detect(options.client);
+    if matches!(app.handoff, Handoff::Switch { .. }) {
+        app.clients = tmux.list_clients().unwrap_or_default();
+    }
+    app.rebuild_rows();
+    // Without a control client the table still refreshes on `r`.
+    for server in &mut app.servers {
+        server.control = server.tmux.subscribe().ok();
+    }
     let mut terminal = ratatui::init();
+    // Pastes arrive as one event instead of keystrokes that could submit.
+    let _ = ratatui::crossterm::execute!(io::stdout(), EnableBracketedPaste);
     let result = app.run(&mut terminal);
+    let _ = ratatui::crossterm::execute!(io::stdout(), DisableBracketedPaste);
     ratatui::restore();
-    result
+    result?;
+    // Outside tmux the terminal is handed to tmux only once the TUI is gone.
+    if let Some((server, target)) = app.pending_attach.take() {
+        app.servers[server].tmux.open(&Handoff::Attach, &target)?;
+    }
+    Ok(())
 }
 
 pub fn run_pane_selector(
+    tmux: &dyn TmuxClient,
+    store: ContextStore,
     session_name: String,
     pane_id: String,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut selector = PaneSelector::new(session_name, pane_id)?;
+    let mut selector = PaneSelector::new(store, session_name, pane_id)?;
     let mut terminal = ratatui::init();
     let result = selector.run(&mut terminal);
     ratatui::restore();
-    result
+    result?;
+    let _ = crate::mirror::pane(
+        tmux,
+        &selector.store,
+        &selector.session_name,
+        &selector.pane_id,
+    );
+    Ok(())
 }
 
 #[derive(Clone)]
 struct SessionRow {
+    /// Index into `App::servers`.
+    server: usize,
     id: String,
     name: String,
     status: Option<crate::context::AgentStatus>,
+    inferred: Option<crate::context::AgentStatus>,
     context: String,
+    attached: u32,
+    windows: u32,
+    created: Option<u64>,
+    activity: Option<u64>,
+    last_attached: Option<u64>,
+    /// Last time a client switched to the session, from the store.
+    seen: Option<u64>,
+    /// Last time its status or context was reported, from the store.
+    updated: Option<u64>,
     panes: Vec<PaneRow>,
 }
 
+impl SessionRow {
+    /// Takes the stored status, context and times of the session and its
+    /// panes from its entry in the store.
+    fn apply_context(&mut self, context: Option<&crate::context::SessionContext>) {
+        self.status = context.and_then(|ctx| ctx.status.clone());
+        self.context = normalize_field(context.and_then(|ctx| ctx.context.as_ref()));
+        self.seen = context.and_then(|ctx| ctx.seen_at);
+        self.updated = context.and_then(|ctx| ctx.updated_at);
+        for pane in &mut self.panes {
+            let stored = context.and_then(|ctx| ctx.panes.get(&pane.id));
+            pane.status = stored.and_then(|pane| pane.status.clone());
+            pane.context = normalize_field(stored.and_then(|pane| pane.context.as_ref()));
+            pane.updated = stored.and_then(|pane| pane.updated_at);
+        }
+    }
+
+    /// The session's own status and its panes', as filters and sorting see
+    /// them.
+    fn statuses(&self, infer: bool) -> impl Iterator<Item = &crate::context::AgentStatus> {
+        let own = filter_status(self.status.as_ref(), self.inferred.as_ref(), infer);
+        self.panes
+            .iter()
+            .filter_map(move |pane| {
+                filter_status(pane.status.as_ref(), pane.inferred.as_ref(), infer)
+            })
+            .chain(own)
+    }
+}
+
 #[derive(Clone)]
 struct PaneRow {
+    server: usize,
     id: String,
     status: Option<crate::context::AgentStatus>,
+    context: String,
+    inferred: Option<crate::context::AgentStatus>,
     session_id: String,
+    window_id: String,
+    window: String,
+    /// Window and pane indexes, for sorting by name.
+    window_index: u32,
+    pane_index: u32,
+    /// The active pane of its window, in the session's active window.
+    active: bool,
+    window_active: bool,
+    /// The pane a client attached to the session is looking at.
+    current: bool,
+    command: String,
+    path: String,
+    title: String,
+    pid: Option<u32>,
+    activity: Option<u64>,
+    updated: Option<u64>,
+}
+
+/// Heads a server's sessions when more than one server is listed; it
+/// cannot be selected.
+#[derive(Clone)]
+struct ServerRow {
+    index: usize,
+    label: String,
+    sessions: usize,
 }
 
 #[derive(Clone)]
 enum RowItem {
+    Server(ServerRow),
     Session(SessionRow),
     Pane(PaneRow),
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 enum RowKey {
-    Session(String),
-    Pane { session_id: String, pane_id: String },
+    Server(usize),
+    Session {
+        server: usize,
+        id: String,
+    },
+    Pane {
+        server: usize,
+        session_id: String,
+        pane_id: String,
+    },
 }
 
 impl RowItem {
     fn key(&self) -> RowKey {
         match self {
-            RowItem::Session(row) => RowKey::Session(row.id.clone()),
+            RowItem::Server(row) => RowKey::Server(row.index),
+            RowItem::Session(row) => RowKey::Session {
+                server: row.server,
+                id: row.id.clone(),
+            },
             RowItem::Pane(row) => RowKey::Pane {
+                server: row.server,
                 session_id: row.session_id.clone(),
                 pane_id: row.id.clone(),
             },
         }
     }
+
+    /// The server and id of the session a session or pane row belongs to.
+    fn session(&self) -> Option<(usize, &str)> {
+        match self {
+            RowItem::Server(_) => None,
+            RowItem::Session(row) => Some((row.server, row.id.as_str())),
+            RowItem::Pane(row) => Some((row.server, row.session_id.as_str())),
+        }
+    }
+}
+
+/// A tmux server the TUI lists sessions from.
+struct Server<'a> {
+    label: String,
+    socket: TmuxSocket,
+    tmux: &'a dyn TmuxClient,
+    /// Whether jkl runs inside this server, so clients can switch to it
+    /// directly.
+    current: bool,
+    control: Option<ControlStream>,
+    /// When to try again after the control client went away, and the delay
+    /// after that.
+    reconnect_at: Option<Instant>,
+    reconnect_delay: Duration,
+}
+
+impl<'a> Server<'a> {
+    fn new(socket: TmuxSocket, tmux: &'a dyn TmuxClient) -> Self {
+        Self {
+            label: socket.label(),
+            current: socket.is_current(),
+            socket,
+            tmux,
+            control: None,
+            reconnect_at: None,
+            reconnect_delay: RECONNECT_DELAY,
+        }
+    }
+
+    /// The one server of a TUI that does not aggregate servers.
+    fn only(tmux: &'a dyn TmuxClient) -> Self {
+        Self {
+            label: TmuxSocket::Default.label(),
+            socket: TmuxSocket::Default,
+            tmux,
+            current: true,
+            control: None,
+            reconnect_at: None,
+            reconnect_delay: RECONNECT_DELAY,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SessionSort {
+    #[default]
+    Tmux,
+    Activity,
+    Attached,
+    Created,
+    Name,
+    /// Most pressing status first, counting a session's panes.
+    Status,
+    /// Last reported status or context change.
+    Updated,
+    /// Waiting agents, longest waiting first.
+    Waiting,
+}
+
+impl SessionSort {
+    fn next(self) -> Self {
+        match self {
+            SessionSort::Tmux => SessionSort::Activity,
+            SessionSort::Activity => SessionSort::Attached,
+            SessionSort::Attached => SessionSort::Created,
+            SessionSort::Created => SessionSort::Name,
+            SessionSort::Name => SessionSort::Status,
+            SessionSort::Status => SessionSort::Updated,
+            SessionSort::Updated => SessionSort::Waiting,
+            SessionSort::Waiting => SessionSort::Tmux,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SessionSort::Tmux => "tmux",
+            SessionSort::Activity => "activity",
+            SessionSort::Attached => "attached",
+            SessionSort::Created => "created",
+            SessionSort::Name => "name",
+            SessionSort::Status => "status",
+            SessionSort::Updated => "updated",
+            SessionSort::Waiting => "waiting",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        let mut sort = SessionSort::Tmux;
+        loop {
+            if sort.label() == label {
+                return Some(sort);
+            }
+            sort = sort.next();
+            if sort == SessionSort::Tmux {
+                return None;
+            }
+        }
+    }
+
+    /// The order each mode starts in: the most recent, attached, pressing
+    /// or longest waiting first, names from A.
+    fn default_order(self) -> SortOrder {
+        match self {
+            SessionSort::Activity
+            | SessionSort::Attached
+            | SessionSort::Created
+            | SessionSort::Updated => SortOrder::Descending,
+            SessionSort::Tmux | SessionSort::Name | SessionSort::Status | SessionSort::Waiting => {
+                SortOrder::Ascending
+            }
+        }
+    }
+
+    /// Sorts sessions and the panes within each. Ties keep tmux order, and
+    /// rows the mode has nothing to go on (no activity, not waiting) go last.
+    fn apply(self, order: SortOrder, sessions: &mut [SessionRow], infer: bool) {
+        for session in sessions.iter_mut() {
+            self.apply_panes(order, &mut session.panes, infer);
+        }
+        match self {
+            SessionSort::Tmux => {
+                if order == SortOrder::Descending {
+                    sessions.reverse();
+                }
+            }
+            SessionSort::Activity => sort_rows(sessions, order, |row| row.activity),
+            SessionSort::Attached => sort_rows(sessions, order, |row| {
+                Some((row.attached > 0, row.activity))
+            }),
+            SessionSort::Created => sort_rows(sessions, order, |row| row.created),
+            SessionSort::Name => sort_rows(sessions, order, |row| Some(row.name.to_lowercase())),
+            SessionSort::Status => sort_rows(sessions, order, |row| {
+                crate::infer::session_status(row.statuses(infer))
+                    .map(|status| crate::infer::urgency(&status))
+            }),
+            SessionSort::Updated => sort_rows(sessions, order, |row| {
+                row.panes
+                    .iter()
+                    .map(|pane| pane.updated)
+                    .fold(row.updated, Option::max)
+            }),
+            SessionSort::Waiting => sort_rows(sessions, order, |row| {
+                let own = waiting_since(
+                    row.status.as_ref(),
+                    row.inferred.as_ref(),
+                    row.updated,
+                    infer,
+                );
+                row.panes
+                    .iter()
+                    .filter_map(|pane| {
+                        waiting_since(
+                            pane.status.as_ref(),
+                            pane.inferred.as_ref(),
+                            pane.updated,
+                            infer,
+                        )
+                    })
+                    .chain(own)
+                    .min()
+            }),
+        }
+    }
+
+    fn apply_panes(self, order: SortOrder, panes: &mut [PaneRow], infer: bool) {
+        match self {
+            SessionSort::Tmux => {
+                if order == SortOrder::Descending {
+                    panes.reverse();
+                }
+            }
+            SessionSort::Activity => sort_rows(panes, order, |row| row.activity),
+            SessionSort::Attached => {
+                sort_rows(panes, order, |row| Some((row.current, row.activity)))
+            }
+            // tmux does not report when a pane was created.
+            SessionSort::Created => {}
+            SessionSort::Name => {
+                sort_rows(panes, order, |row| Some((row.window_index, row.pane_index)))
+            }
+            SessionSort::Status => sort_rows(panes, order, |row| {
+                filter_status(row.status.as_ref(), row.inferred.as_ref(), infer)
+                    .map(crate::infer::urgency)
+            }),
+            SessionSort::Updated => sort_rows(panes, order, |row| row.updated),
+            SessionSort::Waiting => sort_rows(panes, order, |row| {
+                waiting_since(
+                    row.status.as_ref(),
+                    row.inferred.as_ref(),
+                    row.updated,
+                    infer,
+                )
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    fn toggle(self) -> Self {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "asc",
+            SortOrder::Descending => "desc",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "asc" => Some(SortOrder::Ascending),
+            "desc" => Some(SortOrder::Descending),
+            _ => None,
+        }
+    }
+
+    fn arrow(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "↑",
+            SortOrder::Descending => "↓",
+        }
+    }
+}
+
+/// Stable sort by `key` in `order`; rows without a key go last either way.
+fn sort_rows<T, K: Ord>(rows: &mut [T], order: SortOrder, key: impl Fn(&T) -> Option<K>) {
+    rows.sort_by(|a, b| match (key(a), key(b)) {
+        (Some(a), Some(b)) if order == SortOrder::Ascending => a.cmp(&b),
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+}
+
+/// When a waiting row last reported, so the oldest report waited longest.
+/// Waiting rows that never reported a time sort after the rest.
+fn waiting_since(
+    status: Option<&crate::context::AgentStatus>,
+    inferred: Option<&crate::context::AgentStatus>,
+    updated: Option<u64>,
+    infer: bool,
+) -> Option<u64> {
+    (filter_status(status, inferred, infer) == Some(&crate::context::AgentStatus::Waiting))
+        .then(|| updated.unwrap_or(u64::MAX))
+}
+
+/// Captured output of the selected pane, shown below the table.
+#[derive(Default)]
+struct Preview {
+    /// The server index and the session or pane id captured; ids repeat
+    /// across servers.
+    target: Option<(usize, String)>,
+    text: Text<'static>,
+    /// Lines scrolled up from the bottom of the capture.
+    scroll_back: u16,
+    captured_at: Option<Instant>,
+}
+
+/// A dialog drawn over the table that takes all key input while open.
+enum Overlay {
+    NewSession(NewSessionForm),
+    Kill {
+        /// Paired with the index of the server they are on.
+        targets: Vec<(usize, KillTarget)>,
+        dialog: ConfirmDialog,
+    },
+    Rename {
+        server: usize,
+        session_id: String,
+        old_name: String,
+        dialog: PromptDialog,
+    },
+    /// Sets the status of a session or pane row.
+    Status {
+        key: RowKey,
+        dialog: StatusDialog,
+    },
+    /// Edits the context of a session or pane row.
+    Context {
+        key: RowKey,
+        dialog: PromptDialog,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Exit,
 }
 
-struct App {
+struct App<'a> {
+    /// The server jkl runs inside, or the one it was pointed at.
+    tmux: &'a dyn TmuxClient,
+    servers: Vec<Server<'a>>,
+    store: ContextStore,
     state: TableState,
     sessions: Vec<SessionRow>,
     filtered_sessions: Vec<SessionRow>,
     rows: Vec<RowItem>,
-    widths: (u16, u16, u16),
+    widths: (u16, u16, u16, u16),
     search_query: String,
+    /// Why `search_query` does not parse, shown in the search bar.
+    search_error: Option<String>,
     search_mode: bool,
-    expanded_sessions: HashSet<String>,
+    search_backend: SearchBackend,
+    expanded_sessions: HashSet<(usize, String)>,
+    /// Sessions shown expanded because the search matched their panes.
+    search_expanded: HashSet<(usize, String)>,
+    /// Rows picked with Space for a bulk kill.
+    marked: HashSet<RowKey>,
+    sort: SessionSort,
+    sort_order: SortOrder,
+    attached_only: bool,
+    infer: bool,
+    handoff: Handoff,
+    /// Clients `c` cycles through when more than one is attached.
+    clients: Vec<AttachedClient>,
+    pending_attach: Option<(usize, Target)>,
+    preview: Option<Preview>,
+    /// Set while the board view replaces the table.
+    board: Option<Board>,
+    overlay: Option<Overlay>,
+    /// Set while tmux is missing or no server is running; the table then
+    /// shows an empty state instead of sessions.
+    unavailable: Option<TmuxError>,
 }
 
-impl App {
-    fn new(sessions: Vec<SessionRow>) -> Result<Self, Box<dyn std::error::Error>> {
+impl<'a> App<'a> {
+    /// Starts empty when tmux cannot be reached; see `unavailable`.
+    fn load(
+        tmux: &'a dyn TmuxClient,
+        store: ContextStore,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_servers(tmux, vec![Server::only(tmux)], store)
+    }
+
+    /// Lists the sessions of several servers; `tmux` is the one jkl runs in.
+    fn load_servers(
+        tmux: &'a dyn TmuxClient,
+        servers: Vec<Server<'a>>,
+        store: ContextStore,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut app = Self::new(tmux, store, Vec::new())?;
+        app.servers = servers;
+        // A missing or unreadable settings file just means defaults.
+        let settings = app.store.settings().unwrap_or_default();
+        if let Some(sort) = settings.sort.as_deref().and_then(SessionSort::from_label) {
+            app.sort = sort;
+            app.sort_order = sort.default_order();
+        }
+        if let Some(order) = settings
+            .sort_order
+            .as_deref()
+            .and_then(SortOrder::from_label)
+        {
+            app.sort_order = order;
+        }
+        app.reload_data()?;
+        Ok(app)
+    }
+
+    fn new(
+        tmux: &'a dyn TmuxClient,
+        store: ContextStore,
+        sessions: Vec<SessionRow>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let mut app = Self {
+            tmux,
+            servers: vec![Server::only(tmux)],
+            store,
             state: TableState::default(),
             filtered_sessions: sessions.clone(),
             sessions,
             rows: Vec::new(),
-            widths: (0, 0, 0),
+            widths: (0, 0, 0, 0),
             search_query: String::new(),
+            search_error: None,
             search_mode: false,
+            search_backend: SearchBackend::default(),
             expanded_sessions: HashSet::new(),
+            search_expanded: HashSet::new(),
+            marked: HashSet::new(),
+            sort: SessionSort::default(),
+            sort_order: SortOrder::default(),
+            attached_only: false,
+            infer: false,
+            handoff: Handoff::Switch { client: None },
+            clients: Vec::new(),
+            pending_attach: None,
+            preview: None,
+            board: None,
+            overlay: None,
+            unavailable: None,
         };
         app.rebuild_rows();
         app.ensure_selection();
@@ -105,92 +672,628 @@ impl App {
 
     fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Box<dyn std::error::Error>> {
         loop {
+            self.update_preview();
             terminal.draw(|frame| self.draw(frame))?;
 
-            if let Event::Key(key) = event::read()? {
-                if key.kind != KeyEventKind::Press {
-                    continue;
+            if event::poll(EVENT_POLL_INTERVAL)? {
+                match event::read()? {
+                    Event::Key(key) if self.handle_key(key)? == Flow::Exit => return Ok(()),
+                    Event::Paste(text) => self.handle_paste(&text)?,
+                    _ => {}
                 }
+            }
+            self.process_control_events()?;
+        }
+    }
 
-                if self.search_mode {
-                    match key.code {
-                        KeyCode::Esc => {
-                            self.search_mode = false;
-                        }
-                        KeyCode::Enter => {
-                            self.switch_selected()?;
-                            return Ok(());
-                        }
-                        KeyCode::Backspace => {
-                            self.search_query.pop();
-                            self.apply_search()?;
+    /// Applies control notifications in place where the rows carry what
+    /// changed, and reloads only servers whose sessions, windows or panes
+    /// came or went. A control client that went away is reconnected with
+    /// backoff, and its server reloaded for whatever happened meanwhile.
+    fn process_control_events(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut reload = Vec::new();
+        let mut changed = false;
+        let mut contexts = None;
+        for server in 0..self.servers.len() {
+            if self.reconnect_due(server) {
+                reload.push(server);
+            }
+            let Some(control) = self.servers[server].control.as_ref() else {
+                continue;
+            };
+            for event in control.drain() {
+                if event != ControlEvent::Exit {
+                    self.servers[server].reconnect_delay = RECONNECT_DELAY;
+                }
+                match event {
+                    ControlEvent::SessionRenamed { session_id, name } => {
+                        let contexts = match &mut contexts {
+                            Some(contexts) => contexts,
+                            None => contexts.insert(self.store.load()?),
+                        };
+                        changed |= self.rename_session_row(server, &session_id, &name, contexts);
+                    }
+                    ControlEvent::WindowRenamed { window_id, name } => {
+                        for pane in self.server_panes(server) {
+                            if pane.window_id == window_id {
+                                pane.window = format!("{}:{name}", pane.window_index);
+                                changed = true;
+                            }
                         }
-                        KeyCode::Down => self.next_row(),
-                        KeyCode::Up => self.previous_row(),
-                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                            self.search_mode = false;
+                    }
+                    ControlEvent::WindowPaneChanged { window_id, pane_id } => {
+                        for pane in self.server_panes(server) {
+                            if pane.window_id == window_id {
+                                pane.active = pane.id == pane_id;
+                                pane.current = pane.active && pane.window_active;
+                                changed = true;
+                            }
                         }
-                        KeyCode::Char(c) => {
-                            self.search_query.push(c);
-                            self.apply_search()?;
+                    }
+                    ControlEvent::SessionWindowChanged {
+                        session_id,
+                        window_id,
+                    } => {
+                        for pane in self.server_panes(server) {
+                            if pane.session_id == session_id {
+                                pane.window_active = pane.window_id == window_id;
+                                pane.current = pane.active && pane.window_active;
+                                changed = true;
+                            }
                         }
-                        _ => {}
                     }
-                } else {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
-                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                            return Ok(());
+                    ControlEvent::PaneModeChanged { pane_id } => {
+                        match self.servers[server].tmux.describe_pane(&pane_id) {
+                            Ok(described) => changed |= self.update_pane_row(server, described),
+                            Err(_) => reload.push(server),
                         }
-                        KeyCode::Char('/') => {
-                            self.search_mode = true;
-                            self.apply_search()?;
+                    }
+                    ControlEvent::SessionsChanged
+                    | ControlEvent::WindowAdd { .. }
+                    | ControlEvent::WindowClose { .. }
+                    | ControlEvent::LayoutChange { .. } => reload.push(server),
+                    ControlEvent::Exit => {
+                        let server = &mut self.servers[server];
+                        server.control = None;
+                        server.reconnect_at = Some(Instant::now() + server.reconnect_delay);
+                        server.reconnect_delay =
+                            (server.reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+                    }
+                    // The control client's own session.
+                    ControlEvent::SessionChanged { .. } => {}
+                }
+            }
+        }
+
+        reload.sort_unstable();
+        reload.dedup();
+        if !reload.is_empty() {
+            self.reload_servers(&reload)?;
+        } else if changed {
+            self.apply_search()?;
+        }
+        Ok(())
+    }
+
+    /// Reconnects `server`'s control client once its delay has passed;
+    /// true when it did. The delay only resets once the new client delivers
+    /// something, so one that exits straight away, as against a stopped
+    /// server, keeps backing off.
+    fn reconnect_due(&mut self, server: usize) -> bool {
+        let server = &mut self.servers[server];
+        if server.reconnect_at.is_none_or(|at| at > Instant::now()) {
+            return false;
+        }
+        server.control = server.tmux.subscribe().ok();
+        if server.control.is_some() {
+            server.reconnect_at = None;
+            return true;
+        }
+        server.reconnect_at = Some(Instant::now() + server.reconnect_delay);
+        server.reconnect_delay = (server.reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+        false
+    }
+
+    fn server_panes(&mut self, server: usize) -> impl Iterator<Item = &mut PaneRow> {
+        self.sessions
+            .iter_mut()
+            .filter(move |session| session.server == server)
+            .flat_map(|session| session.panes.iter_mut())
+    }
+
+    /// Refreshes a pane row from tmux's description of the pane.
+    fn update_pane_row(&mut self, server: usize, described: crate::tmux::TmuxPane) -> bool {
+        let Some(session) = self.sessions.iter_mut().find(|session| {
+            session.server == server
+                && session
+                    .panes
+                    .iter()
+                    .any(|pane| pane.id == described.pane_id)
+        }) else {
+            return false;
+        };
+        let Some(pane) = session
+            .panes
+            .iter_mut()
+            .find(|pane| pane.id == described.pane_id)
+        else {
+            return false;
+        };
+        pane.inferred = crate::infer::pane_status(&described, unix_now());
+        pane.active = described.active;
+        pane.window_active = described.window_active;
+        pane.current = described.active && described.window_active;
+        pane.command = described.current_command;
+        pane.path = described.current_path;
+        pane.title = described.title;
+        pane.pid = described.pid;
+        pane.activity = described.activity;
+        session.inferred = crate::infer::session_status(
+            session
+                .panes
+                .iter()
+                .filter_map(|pane| pane.inferred.as_ref()),
+        );
+        true
+    }
+
+    /// Renames a session row and takes the status and context stored under
+    /// its new name, where hooks have moved them.
+    fn rename_session_row(
+        &mut self,
+        server: usize,
+        session_id: &str,
+        name: &str,
+        contexts: &HashMap<String, crate::context::SessionContext>,
+    ) -> bool {
+        let Some(session) = self
+            .sessions
+            .iter_mut()
+            .find(|row| row.server == server && row.id == session_id)
+        else {
+            return false;
+        };
+        session.name = name.to_string();
+        session.apply_context(contexts.get(&crate::context::session_key(name)));
+        true
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<Flow, Box<dyn std::error::Error>> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(Flow::Continue);
+        }
+
+        if self.overlay.is_some() {
+            self.handle_overlay_key(key)?;
+            return Ok(Flow::Continue);
+        }
+
+        if self.search_mode {
+            match key.code {
+                KeyCode::Esc => {
+                    self.search_mode = false;
+                }
+                KeyCode::Enter => {
+                    self.switch_selected()?;
+                    return Ok(Flow::Exit);
+                }
+                KeyCode::Backspace => {
+                    self.search_query.pop();
+                    self.apply_search()?;
+                }
+                KeyCode::Down => self.next_row(),
+                KeyCode::Up => self.previous_row(),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.search_mode = false;
+                }
+                KeyCode::Char(c) => {
+                    self.search_query.push(c);
+                    self.apply_search()?;
+                }
+                _ => {}
+            }
+        } else {
+            if self.board.is_some() {
+                if let Some(flow) = self.handle_board_key(key)? {
+                    return Ok(flow);
+                }
+                // Keys that act on the table selection stay with the table.
+                if !matches!(
+                    key.code,
+                    KeyCode::Esc
+                        | KeyCode::Char(
+                            'q' | '/' | 'r' | 'o' | 'O' | 'a' | 'i' | 'c' | 'n' | 's' | 'S' | 'e'
+                        )
+                ) {
+                    return Ok(Flow::Continue);
+                }
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(Flow::Exit),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(Flow::Exit);
+                }
+                KeyCode::Char('/') => {
+                    self.search_mode = true;
+                    self.apply_search()?;
+                }
+                KeyCode::Enter => {
+                    self.switch_selected()?;
+                    return Ok(Flow::Exit);
+                }
+                KeyCode::Char('j') | KeyCode::Down => self.next_row(),
+                KeyCode::Char('k') | KeyCode::Up => self.previous_row(),
+                KeyCode::Char('l') => self.expand_selected(),
+                KeyCode::Char('h') => self.collapse_selected(),
+                KeyCode::Char('r') => {
+                    self.refresh_panes()?;
+                }
+                KeyCode::Char('o') => {
+                    self.sort = self.sort.next();
+                    self.sort_order = self.sort.default_order();
+                    self.save_sort()?;
+                    self.apply_search()?;
+                }
+                KeyCode::Char('O') => {
+                    self.sort_order = self.sort_order.toggle();
+                    self.save_sort()?;
+                    self.apply_search()?;
+                }
+                KeyCode::Char('a') => {
+                    self.attached_only = !self.attached_only;
+                    self.apply_search()?;
+                }
+                KeyCode::Char('p') => self.toggle_preview(),
+                KeyCode::Char('i') => {
+                    self.infer = !self.infer;
+                    self.rebuild_rows();
+                }
+                KeyCode::Char('c') => self.cycle_client(),
+                KeyCode::Char('n') => {
+                    self.overlay = Some(Overlay::NewSession(NewSessionForm::default()));
+                }
+                KeyCode::Char('R') => self.prompt_rename(),
+                KeyCode::Char(' ') => self.toggle_mark(),
+                KeyCode::Char('x') => self.confirm_kill(),
+                KeyCode::Char('v') => self.board = Some(Board::default()),
+                KeyCode::Char('s') => self.cycle_status()?,
+                KeyCode::Char('S') => self.prompt_status(),
+                KeyCode::Char('e') => self.prompt_context(),
+                KeyCode::Char('K') => self.scroll_preview(1),
+                KeyCode::Char('J') => self.scroll_preview(-1),
+                KeyCode::PageUp => self.scroll_preview(PREVIEW_PAGE.cast_signed()),
+                KeyCode::PageDown => self.scroll_preview(-PREVIEW_PAGE.cast_signed()),
+                _ => {}
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn handle_overlay_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        // New sessions go to the server of the selected row.
+        let selected_server = self
+            .selected_row()
+            .and_then(RowItem::session)
+            .map_or(0, |(server, _)| server);
+        match self.overlay.as_mut() {
+            Some(Overlay::NewSession(form)) => match form.handle_key(key) {
+                FormAction::Continue => {}
+                FormAction::Cancel => self.overlay = None,
+                FormAction::Submit => {
+                    let request = form.request();
+                    let server = selected_server;
+                    let tmux = self.servers[server].tmux;
+                    match crate::actions::create_session(tmux, &self.store, &request) {
+                        Ok(session) => {
+                            self.overlay = None;
+                            // Creating a session starts a server if none was running.
+                            if self.servers[server].control.is_none() && self.unavailable.is_some()
+                            {
+                                self.servers[server].control = tmux.subscribe().ok();
+                                self.servers[server].reconnect_at = None;
+                            }
+                            self.reload_data()?;
+                            self.restore_selection(Some(RowKey::Session {
+                                server,
+                                id: session.id,
+                            }));
                         }
-                        KeyCode::Enter => {
-                            self.switch_selected()?;
-                            return Ok(());
+                        Err(error) => form.error = Some(error.to_string()),
+                    }
+                }
+            },
+            Some(Overlay::Kill { targets, dialog }) => match dialog.handle_key(key) {
+                FormAction::Continue => {}
+                FormAction::Cancel => self.overlay = None,
+                FormAction::Submit => {
+                    let result = targets.iter().try_for_each(|(server, target)| {
+                        crate::actions::kill(
+                            self.servers[*server].tmux,
+                            &self.store,
+                            std::slice::from_ref(target),
+                        )
+                    });
+                    match result {
+                        Ok(()) => {
+                            self.overlay = None;
+                            self.marked.clear();
                         }
-                        KeyCode::Char('j') | KeyCode::Down => self.next_row(),
-                        KeyCode::Char('k') | KeyCode::Up => self.previous_row(),
-                        KeyCode::Char('l') => self.expand_selected(),
-                        KeyCode::Char('h') => self.collapse_selected(),
-                        KeyCode::Char('r') => {
-                            self.refresh_panes()?;
+                        Err(error) => dialog.error = Some(error.to_string()),
+                    }
+                    // Some targets may be gone even when a later one failed.
+                    self.reload_data()?;
+                }
+            },
+            Some(Overlay::Rename {
+                server,
+                session_id,
+                old_name,
+                dialog,
+            }) => match dialog.handle_key(key) {
+                FormAction::Continue => {}
+                FormAction::Cancel => self.overlay = None,
+                FormAction::Submit => {
+                    let result = crate::actions::rename_session(
+                        self.servers[*server].tmux,
+                        &self.store,
+                        session_id,
+                        old_name,
+                        dialog.value(),
+                    );
+                    match result {
+                        Ok(()) => {
+                            self.overlay = None;
+                            self.reload_data()?;
                         }
-                        _ => {}
+                        Err(error) => dialog.error = Some(error.to_string()),
                     }
                 }
+            },
+            Some(Overlay::Status {
+                key: target,
+                dialog,
+            }) => match dialog.handle_key(key) {
+                FormAction::Continue => {}
+                FormAction::Cancel => self.overlay = None,
+                FormAction::Submit => {
+                    let (target, status) = (target.clone(), dialog.status());
+                    self.overlay = None;
+                    self.save_metadata(&target, status, None)?;
+                }
+            },
+            Some(Overlay::Context {
+                key: target,
+                dialog,
+            }) => match dialog.handle_key(key) {
+                FormAction::Continue => {}
+                FormAction::Cancel => self.overlay = None,
+                FormAction::Submit => {
+                    let (target, context) = (target.clone(), dialog.optional());
+                    self.overlay = None;
+                    self.save_metadata(&target, None, Some(context))?;
+                }
+            },
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Text pasted into the terminal goes to the open text field or the
+    /// search query.
+    fn handle_paste(&mut self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match self.overlay.as_mut() {
+            Some(Overlay::NewSession(form)) => form.paste(text),
+            Some(Overlay::Rename { dialog, .. } | Overlay::Context { dialog, .. }) => {
+                dialog.paste(text);
             }
+            Some(Overlay::Kill { .. } | Overlay::Status { .. }) => {}
+            None if self.search_mode => {
+                self.search_query
+                    .push_str(&text.replace(['\r', '\n', '\t'], " "));
+                self.apply_search()?;
+            }
+            None => {}
         }
+        Ok(())
     }
 
-    fn next_row(&mut self) {
-        if self.rows.is_empty() {
+    /// The selected session or pane: the board's card while the board is
+    /// shown, otherwise the table row.
+    fn selected_item(&self) -> Option<RowItem> {
+        let item = match &self.board {
+            Some(board) => {
+                let columns = board::columns(&self.filtered_sessions, self.infer);
+                board.selected(&columns).map(|card| card.item.clone())
+            }
+            None => self.selected_row().cloned(),
+        };
+        item.filter(|item| !matches!(item, RowItem::Server(_)))
+    }
+
+    /// `api` for a session, `%2 in api` for a pane.
+    fn describe(&self, item: &RowItem) -> String {
+        match item {
+            RowItem::Pane(pane) => {
+                let session = self
+                    .sessions
+                    .iter()
+                    .find(|row| row.server == pane.server && row.id == pane.session_id)
+                    .map_or(pane.session_id.as_str(), |row| row.name.as_str());
+                format!("{} in {session}", pane.id)
+            }
+            item => row_label(item),
+        }
+    }
+
+    /// Steps the selected row's reported status through the status list.
+    fn cycle_status(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(item) = self.selected_item() else {
+            return Ok(());
+        };
+        let options = pane_status_options();
+        let next = row_status(&item)
+            .and_then(|current| {
+                options
+                    .iter()
+                    .position(|(_, status)| status.as_ref() == Some(current))
+            })
+            .map_or(0, |index| (index + 1) % options.len());
+        self.save_metadata(&item.key(), options[next].1.clone(), None)
+    }
+
+    fn prompt_status(&mut self) {
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        let title = format!("Status of {}", self.describe(&item));
+        self.overlay = Some(Overlay::Status {
+            key: item.key(),
+            dialog: StatusDialog::new(title, row_status(&item)),
+        });
+    }
+
+    fn prompt_context(&mut self) {
+        let Some(item) = self.selected_item() else {
             return;
+        };
+        let context = row_context(&item);
+        let context = filter_context(&context).unwrap_or_default();
+        let title = format!("Context of {}", self.describe(&item));
+        self.overlay = Some(Overlay::Context {
+            key: item.key(),
+            dialog: PromptDialog::new(title, context),
+        });
+    }
+
+    /// Opens the rename dialog for the selected session, or a pane's session.
+    fn prompt_rename(&mut self) {
+        let Some((server, session_id)) = self.selected_row().and_then(RowItem::session) else {
+            return;
+        };
+        let Some(session) = self
+            .sessions
+            .iter()
+            .find(|row| row.server == server && row.id == session_id)
+        else {
+            return;
+        };
+        self.overlay = Some(Overlay::Rename {
+            server,
+            session_id: session.id.clone(),
+            old_name: session.name.clone(),
+            dialog: PromptDialog::new(format!("Rename {}", session.name), session.name.clone()),
+        });
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(key) = self
+            .selected_key()
+            .filter(|key| !matches!(key, RowKey::Server(_)))
+        else {
+            return;
+        };
+        if !self.marked.remove(&key) {
+            self.marked.insert(key);
         }
-        let next = match self.state.selected() {
-            Some(index) if index + 1 < self.rows.len() => index + 1,
-            _ => 0,
+        self.next_row();
+    }
+
+    /// Asks before killing the marked rows, or the selected row when none are
+    /// marked. Panes of a marked session go with it and are not listed.
+    fn confirm_kill(&mut self) {
+        let keys = if self.marked.is_empty() {
+            self.selected_key().into_iter().collect::<HashSet<_>>()
+        } else {
+            self.marked.clone()
         };
-        self.state.select(Some(next));
+        let mut targets = Vec::new();
+        let mut lines = Vec::new();
+        for session in &self.sessions {
+            let key = RowKey::Session {
+                server: session.server,
+                id: session.id.clone(),
+            };
+            if keys.contains(&key) {
+                targets.push((
+                    session.server,
+                    KillTarget::Session {
+                        session_id: session.id.clone(),
+                        session_name: session.name.clone(),
+                    },
+                ));
+                lines.push(format!("session {}", session.name));
+                continue;
+            }
+            for pane in &session.panes {
+                let key = RowKey::Pane {
+                    server: session.server,
+                    session_id: session.id.clone(),
+                    pane_id: pane.id.clone(),
+                };
+                if keys.contains(&key) {
+                    targets.push((
+                        session.server,
+                        KillTarget::Pane {
+                            session_id: session.id.clone(),
+                            session_name: session.name.clone(),
+                            pane_id: pane.id.clone(),
+                        },
+                    ));
+                    lines.push(format!("pane {} in {}", pane.id, session.name));
+                }
+            }
+        }
+        if targets.is_empty() {
+            return;
+        }
+        let title = match targets.len() {
+            1 => "Kill?".to_string(),
+            count => format!("Kill {count}?"),
+        };
+        self.overlay = Some(Overlay::Kill {
+            targets,
+            dialog: ConfirmDialog::new(title, lines),
+        });
+    }
+
+    fn next_row(&mut self) {
+        self.step_selection(1);
     }
 
     fn previous_row(&mut self) {
-        if self.rows.is_empty() {
+        self.step_selection(self.rows.len().saturating_sub(1));
+    }
+
+    /// Moves `step` rows forward, wrapping around and skipping server rows.
+    fn step_selection(&mut self, step: usize) {
+        let len = self.rows.len();
+        if len == 0 {
             return;
         }
-        let prev = match self.state.selected() {
-            Some(0) | None => self.rows.len() - 1,
-            Some(index) => index - 1,
-        };
-        self.state.select(Some(prev));
+        // Without a selection `next_row` lands on the first row and
+        // `previous_row` on the last.
+        let mut index = self
+            .state
+            .selected()
+            .unwrap_or(if step == 1 { len - 1 } else { 0 });
+        for _ in 0..len {
+            index = (index + step) % len;
+            if !matches!(self.rows[index], RowItem::Server(_)) {
+                self.state.select(Some(index));
+                return;
+            }
+        }
+    }
+
+    fn first_selectable(&self) -> Option<usize> {
+        self.rows
+            .iter()
+            .position(|row| !matches!(row, RowItem::Server(_)))
     }
 
     fn ensure_selection(&mut self) {
-        if self.rows.is_empty() {
-            self.state.select(None);
-        } else if self.state.selected().is_none() {
-            self.state.select(Some(0));
+        if self.state.selected().is_none() {
+            self.state.select(self.first_selectable());
         }
     }
 
@@ -212,97 +1315,372 @@ impl App {
         &mut self,
         previous: Option<RowKey>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        if self.search_query.trim().is_empty() {
-            self.filtered_sessions = self.sessions.clone();
-            self.rebuild_rows();
-            self.restore_selection(previous);
+        self.search_expanded.clear();
+        let query = match crate::query::Query::parse(&self.search_query) {
+            Ok(query) => {
+                self.search_error = None;
+                query
+            }
+            // Keep the last results while a filter is half typed.
+            Err(error) => {
+                self.search_error = Some(error.to_string());
+                return Ok(());
+            }
+        };
+        if query.is_empty() {
+            let sessions = self.sessions.clone();
+            self.set_filtered(sessions, previous);
             return Ok(());
         }
 
-        let candidates = self
-            .sessions
-            .iter()
-            .map(|row| {
-                format!(
-                    "{}\t{}\t{}\t{}",
-                    row.id,
-                    row.name,
-                    status_text(row.status.as_ref()),
-                    row.context
-                )
-            })
-            .collect::<Vec<_>>();
-
-        let output = run_fzf_filter(&self.search_query, &candidates)?;
-        let mut lines = output.lines();
-        let _ = lines.next();
+        // Field filters pick the candidates; the free text then ranks them.
+        // Sessions and panes are matched as displayed, so every match can be
+        // highlighted. `owners` maps each candidate back to its row.
+        let now = unix_now();
+        let mut candidates = Vec::new();
+        let mut owners = Vec::new();
+        for (session_index, session) in self.sessions.iter().enumerate() {
+            let subject = crate::query::Subject {
+                session_name: &session.name,
+                pane_id: None,
+                status: filter_status(
+                    session.status.as_ref(),
+                    session.inferred.as_ref(),
+                    self.infer,
+                ),
+                context: filter_context(&session.context),
+                updated_at: session.updated,
+            };
+            if query.filters_match(&subject, now) {
+                candidates.push(search_text(&RowItem::Session(session.clone()), self.infer));
+                owners.push((session_index, None));
+            }
+            for (pane_index, pane) in session.panes.iter().enumerate() {
+                let subject = crate::query::Subject {
+                    session_name: &session.name,
+                    pane_id: Some(&pane.id),
+                    status: filter_status(pane.status.as_ref(), pane.inferred.as_ref(), self.infer),
+                    context: filter_context(&pane.context),
+                    updated_at: pane.updated,
+                };
+                if query.filters_match(&subject, now) {
+                    candidates.push(search_text(&RowItem::Pane(pane.clone()), self.infer));
+                    owners.push((session_index, Some(pane_index)));
+                }
+            }
+        }
+        let order = match self.search_backend {
+            // Filters alone keep the table order.
+            _ if query.fuzzy().is_empty() => Some((0..candidates.len()).collect()),
+            SearchBackend::Fzf => run_fzf_filter(query.text(), &candidates).ok(),
+            SearchBackend::Builtin => None,
+        }
+        .unwrap_or_else(|| {
+            crate::fuzzy::filter(query.fuzzy(), &candidates)
+                .into_iter()
+                .map(|(index, _)| index)
+                .collect()
+        });
 
-        let lookup: HashMap<&str, &SessionRow> = self
-            .sessions
-            .iter()
-            .map(|row| (row.id.as_str(), row))
-            .collect();
+        // Sessions rank by their best match, whether on the session or a pane.
+        let mut ranked: Vec<usize> = Vec::new();
+        let mut matched_panes: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (session_index, pane_index) in order.into_iter().filter_map(|index| owners.get(index)) {
+            if !ranked.contains(session_index) {
+                ranked.push(*session_index);
+            }
+            if let Some(pane_index) = pane_index {
+                matched_panes
+                    .entry(*session_index)
+                    .or_default()
+                    .push(*pane_index);
+            }
+        }
         let mut filtered = Vec::new();
-        for line in lines {
-            if let Some(id) = line.split('\t').next() {
-                if let Some(row) = lookup.get(id) {
-                    filtered.push((*row).clone());
-                }
+        for session_index in ranked {
+            let mut session = self.sessions[session_index].clone();
+            // Only the panes that matched are listed, already expanded.
+            if let Some(mut pane_indices) = matched_panes.remove(&session_index) {
+                pane_indices.sort_unstable();
+                session.panes = pane_indices
+                    .into_iter()
+                    .map(|pane_index| session.panes[pane_index].clone())
+                    .collect();
+                self.search_expanded
+                    .insert((session.server, session.id.clone()));
             }
+            filtered.push(session);
         }
-        self.filtered_sessions = filtered;
+        self.set_filtered(filtered, previous);
+        Ok(())
+    }
+
+    fn set_filtered(&mut self, mut sessions: Vec<SessionRow>, previous: Option<RowKey>) {
+        if self.attached_only {
+            sessions.retain(|row| row.attached > 0);
+        }
+        self.sort.apply(self.sort_order, &mut sessions, self.infer);
+        self.filtered_sessions = sessions;
         self.rebuild_rows();
         self.restore_selection(previous);
-        Ok(())
+    }
+
+    fn save_sort(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut settings = self.store.settings().unwrap_or_default();
+        settings.sort = Some(self.sort.label().to_string());
+        settings.sort_order = Some(self.sort_order.label().to_string());
+        self.store.save_settings(&settings)
     }
 
     fn rebuild_rows(&mut self) {
         let mut rows = Vec::new();
-        for session in &self.filtered_sessions {
-            rows.push(RowItem::Session(session.clone()));
-            if self.expanded_sessions.contains(&session.id) {
-                for pane in &session.panes {
-                    rows.push(RowItem::Pane(pane.clone()));
+        let grouped = self.servers.len() > 1;
+        for (index, server) in self.servers.iter().enumerate() {
+            let sessions = self
+                .filtered_sessions
+                .iter()
+                .filter(|session| session.server == index)
+                .collect::<Vec<_>>();
+            if grouped && !sessions.is_empty() {
+                rows.push(RowItem::Server(ServerRow {
+                    index,
+                    label: server.label.clone(),
+                    sessions: sessions.len(),
+                }));
+            }
+            for session in sessions {
+                rows.push(RowItem::Session(session.clone()));
+                let key = (session.server, session.id.clone());
+                if self.expanded_sessions.contains(&key) || self.search_expanded.contains(&key) {
+                    for pane in &session.panes {
+                        rows.push(RowItem::Pane(pane.clone()));
+                    }
                 }
             }
         }
         self.rows = rows;
-        self.widths = measure_widths(&self.rows);
+        self.widths = measure_widths(&self.rows, self.infer);
     }
 
     fn restore_selection(&mut self, previous: Option<RowKey>) {
-        if self.rows.is_empty() {
-            self.state.select(None);
+        if let Some(key) = previous
+            && let Some(index) = self.rows.iter().position(|row| row.key() == key)
+        {
+            self.state.select(Some(index));
             return;
         }
-        if let Some(key) = previous {
-            if let Some(index) = self.rows.iter().position(|row| row.key() == key) {
-                self.state.select(Some(index));
-                return;
+        self.state.select(self.first_selectable());
+    }
+
+    /// Switches to the highlighted card on the board, else the table row.
+    fn switch_selected(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.selected_item() {
+            Some(item) => self.switch_to(&item),
+            None => Ok(()),
+        }
+    }
+
+    fn switch_to(&mut self, item: &RowItem) -> Result<(), Box<dyn std::error::Error>> {
+        let (server, target) = match item {
+            RowItem::Session(session) => (session.server, Target::session(&session.id)),
+            RowItem::Pane(pane) => (
+                pane.server,
+                Target::pane(&pane.session_id, &pane.window_id, &pane.id),
+            ),
+            RowItem::Server(_) => return Ok(()),
+        };
+        if self.handoff == Handoff::Attach {
+            self.pending_attach = Some((server, target));
+            return Ok(());
+        }
+        let server = &self.servers[server];
+        match &self.handoff {
+            Handoff::Switch { client } if !server.current => crate::tmux::open_on_server(
+                self.tmux,
+                client.as_deref(),
+                server.tmux,
+                &server.socket,
+                &target,
+            )?,
+            handoff => server.tmux.open(handoff, &target)?,
+        }
+        Ok(())
+    }
+
+    /// Board navigation; `None` for keys the board leaves to the table.
+    fn handle_board_key(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<Option<Flow>, Box<dyn std::error::Error>> {
+        let Some(board) = self.board.as_mut() else {
+            return Ok(None);
+        };
+        let columns = board::columns(&self.filtered_sessions, self.infer);
+        match key.code {
+            KeyCode::Char('v') => self.board = None,
+            KeyCode::Char('h') | KeyCode::Left => board.step_column(&columns, -1),
+            KeyCode::Char('l') | KeyCode::Right => board.step_column(&columns, 1),
+            KeyCode::Char('k') | KeyCode::Up => board.step_card(&columns, -1),
+            KeyCode::Char('j') | KeyCode::Down => board.step_card(&columns, 1),
+            KeyCode::Enter => {
+                if let Some(card) = board.selected(&columns) {
+                    let item = card.item.clone();
+                    self.switch_to(&item)?;
+                    return Ok(Some(Flow::Exit));
+                }
             }
+            KeyCode::Char(c @ ('H' | 'L')) => {
+                let Some(card) = board.selected(&columns) else {
+                    return Ok(Some(Flow::Continue));
+                };
+                let step = if c == 'H' { -1 } else { 1 };
+                let column = board
+                    .column
+                    .saturating_add_signed(step)
+                    .min(board::COLUMNS.len() - 1);
+                if column != board.column {
+                    let key = card.item.key();
+                    self.save_metadata(&key, Some(board::COLUMNS[column].clone()), None)?;
+                }
+            }
+            _ => return Ok(None),
         }
-        self.state.select(Some(0));
+        Ok(Some(Flow::Continue))
     }
 
-    fn switch_selected(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(row) = self.selected_row() {
-            let session_id = match row {
-                RowItem::Session(session) => session.id.as_str(),
-                RowItem::Pane(pane) => pane.session_id.as_str(),
-            };
-            crate::tmux::switch_client(session_id)?;
+    /// Stores a session or pane row's status and/or context, copies them to
+    /// the tmux options and reloads. `Some(None)` clears the context. The
+    /// board follows the row if its card changes column.
+    fn save_metadata(
+        &mut self,
+        key: &RowKey,
+        status: Option<crate::context::AgentStatus>,
+        context: Option<Option<String>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (server, session_id) = match key {
+            RowKey::Session { server, id } => (*server, id),
+            RowKey::Pane {
+                server, session_id, ..
+            } => (*server, session_id),
+            RowKey::Server(_) => return Ok(()),
+        };
+        let Some(session_name) = self
+            .sessions
+            .iter()
+            .find(|row| row.server == server && &row.id == session_id)
+            .map(|row| row.name.clone())
+        else {
+            return Ok(());
+        };
+        let tmux = self.servers[server].tmux;
+        let clear = context == Some(None);
+        let context = context.flatten();
+        if let RowKey::Pane { pane_id, .. } = key {
+            self.store
+                .upsert_pane(&session_name, pane_id, status, context)?;
+            if clear {
+                self.store.clear_context(&session_name, Some(pane_id))?;
+            }
+            let _ = crate::mirror::pane(tmux, &self.store, &session_name, pane_id);
+        } else {
+            self.store.upsert_session(
+                session_name.clone(),
+                Some(session_id.clone()),
+                status,
+                context,
+            )?;
+            if clear {
+                self.store.clear_context(&session_name, None)?;
+            }
+            let _ = crate::mirror::session(tmux, &self.store, &session_name);
+        }
+        self.reload_data()?;
+        if let Some(board) = self.board.as_mut() {
+            let columns = board::columns(&self.filtered_sessions, self.infer);
+            board.select(&columns, key);
         }
         Ok(())
     }
 
+    /// Steps through the current client followed by each attached client.
+    fn cycle_client(&mut self) {
+        let Handoff::Switch { client } = &mut self.handoff else {
+            return;
+        };
+        let next = match client.as_ref() {
+            None => 0,
+            Some(name) => match self
+                .clients
+                .iter()
+                .position(|candidate| &candidate.name == name)
+            {
+                Some(index) => index + 1,
+                None => self.clients.len(),
+            },
+        };
+        *client = self
+            .clients
+            .get(next)
+            .map(|candidate| candidate.name.clone());
+    }
+
+    fn toggle_preview(&mut self) {
+        if self.preview.take().is_none() {
+            self.preview = Some(Preview::default());
+            self.update_preview();
+        }
+    }
+
+    /// The selected pane, or for a session row its active pane.
+    fn preview_target(&self) -> Option<(usize, String)> {
+        match self.selected_row()? {
+            RowItem::Server(_) => None,
+            RowItem::Session(session) => Some((session.server, session.id.clone())),
+            RowItem::Pane(pane) => Some((pane.server, pane.id.clone())),
+        }
+    }
+
+    /// Recaptures when the selection moved or the last capture is stale.
+    fn update_preview(&mut self) {
+        let target = self.preview_target();
+        let servers = &self.servers;
+        let Some(preview) = self.preview.as_mut() else {
+            return;
+        };
+        let stale = preview
+            .captured_at
+            .is_none_or(|captured_at| captured_at.elapsed() >= PREVIEW_REFRESH_INTERVAL);
+        if preview.target == target && !stale {
+            return;
+        }
+        if preview.target != target {
+            preview.scroll_back = 0;
+        }
+        preview.text = match &target {
+            Some((server, target)) => match servers[*server].tmux.capture_pane(target) {
+                Ok(output) => ansi::to_text(output.trim_end()),
+                Err(error) => Text::from(error.to_string()),
+            },
+            None => Text::default(),
+        };
+        preview.target = target;
+        preview.captured_at = Some(Instant::now());
+    }
+
+    fn scroll_preview(&mut self, lines: i16) {
+        if let Some(preview) = self.preview.as_mut() {
+            preview.scroll_back = preview.scroll_back.saturating_add_signed(lines);
+        }
+    }
+
     fn expand_selected(&mut self) {
         let previous = self.selected_key();
-        let session_id = self.selected_row().map(|row| match row {
-            RowItem::Session(session) => session.id.clone(),
-            RowItem::Pane(pane) => pane.session_id.clone(),
-        });
-        if let Some(session_id) = session_id {
-            self.expanded_sessions.insert(session_id);
+        let session = self
+            .selected_row()
+            .and_then(RowItem::session)
+            .map(|(server, id)| (server, id.to_string()));
+        if let Some(session) = session {
+            self.expanded_sessions.insert(session);
             self.rebuild_rows();
             self.restore_selection(previous);
         }
@@ -310,31 +1688,122 @@ impl App {
 
     fn collapse_selected(&mut self) {
         let previous = self.selected_key();
-        let session_id = self.selected_row().map(|row| match row {
-            RowItem::Session(session) => session.id.clone(),
-            RowItem::Pane(pane) => pane.session_id.clone(),
-        });
-        if let Some(session_id) = session_id {
-            self.expanded_sessions.remove(&session_id);
+        let session = self
+            .selected_row()
+            .and_then(RowItem::session)
+            .map(|(server, id)| (server, id.to_string()));
+        if let Some(session) = session {
+            self.search_expanded.remove(&session);
+            self.expanded_sessions.remove(&session);
             self.rebuild_rows();
             self.restore_selection(previous);
         }
     }
 
+    /// Like `reload_data`, but first drops stored panes that no longer exist.
     fn refresh_panes(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let live_panes = crate::tmux::list_panes()?;
-        let live_map = collect_live_panes(&live_panes);
-        crate::context::prune_panes(&live_map)?;
-        self.reload_data()?;
-        Ok(())
+        let snapshots = self.load_snapshots()?;
+        let mut live = HashMap::<String, HashSet<String>>::new();
+        for (_, snapshot) in &snapshots {
+            for (session_name, panes) in snapshot.live_panes() {
+                live.entry(session_name).or_default().extend(panes);
+            }
+        }
+        if self.unavailable.is_none() {
+            self.store.prune_panes(&live)?;
+        }
+        self.apply_snapshots(snapshots)
     }
 
     fn reload_data(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let snapshots = self.load_snapshots()?;
+        self.apply_snapshots(snapshots)
+    }
+
+    /// One snapshot per reachable server, with its index. Servers that cannot
+    /// be reached are left out, and only when none can is `unavailable` set.
+    fn load_snapshots(&mut self) -> Result<Vec<(usize, Snapshot)>, Box<dyn std::error::Error>> {
+        let aggregated = self.servers.len() > 1;
+        let mut snapshots = Vec::new();
+        let mut unavailable = None;
+        for (index, server) in self.servers.iter().enumerate() {
+            match server.tmux.snapshot() {
+                Ok(snapshot) => snapshots.push((index, snapshot)),
+                // The last session closing also stops the server, and
+                // discovered sockets may belong to servers that are gone.
+                Err(error) if error.is_unavailable() || aggregated => {
+                    unavailable.get_or_insert(error);
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+        self.unavailable = if snapshots.is_empty() {
+            unavailable
+        } else {
+            None
+        };
+        Ok(snapshots)
+    }
+
+    /// Like `reload_data`, but keeps the rows of servers not in `servers`.
+    fn reload_servers(&mut self, servers: &[usize]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut snapshots = Vec::new();
+        for &server in servers {
+            match self.servers[server].tmux.snapshot() {
+                Ok(snapshot) => snapshots.push((server, snapshot)),
+                // The server may have stopped with its last session; a full
+                // reload works out what is still reachable.
+                Err(_) => return self.reload_data(),
+            }
+        }
+        self.unavailable = None;
+        let contexts = self.store.load()?;
+        let mut sessions = std::mem::take(&mut self.sessions);
+        sessions.retain(|session| !servers.contains(&session.server));
+        sessions.extend(
+            snapshots
+                .into_iter()
+                .flat_map(|(server, snapshot)| build_sessions(server, snapshot, &contexts)),
+        );
+        // Stable, so each server's sessions stay in tmux's order.
+        sessions.sort_by_key(|session| session.server);
+        self.set_sessions(sessions)
+    }
+
+    fn apply_snapshots(
+        &mut self,
+        snapshots: Vec<(usize, Snapshot)>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let contexts = self.store.load()?;
+        let sessions = snapshots
+            .into_iter()
+            .flat_map(|(server, snapshot)| build_sessions(server, snapshot, &contexts))
+            .collect();
+        self.set_sessions(sessions)
+    }
+
+    fn set_sessions(
+        &mut self,
+        sessions: Vec<SessionRow>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let previous = self.selected_key();
-        let sessions = crate::tmux::list_sessions()?;
-        let contexts = crate::context::load_contexts()?;
-        let panes = crate::tmux::list_panes()?;
-        self.sessions = build_sessions(sessions, contexts, panes);
+        self.sessions = sessions;
+        let live = self
+            .sessions
+            .iter()
+            .flat_map(|session| {
+                std::iter::once(RowKey::Session {
+                    server: session.server,
+                    id: session.id.clone(),
+                })
+                .chain(session.panes.iter().map(|pane| RowKey::Pane {
+                    server: session.server,
+                    session_id: session.id.clone(),
+                    pane_id: pane.id.clone(),
+                }))
+            })
+            .collect::<HashSet<_>>();
+        self.marked.retain(|key| live.contains(key));
         self.filtered_sessions = self.sessions.clone();
         self.rebuild_rows();
         self.apply_search_with(previous)?;
@@ -349,8 +1818,57 @@ impl App {
         ]);
         let sections = layout.split(frame.area());
         self.render_search(frame, sections[0]);
-        self.render_table(frame, sections[1]);
+        if let Some(board) = &self.board {
+            let columns = board::columns(&self.filtered_sessions, self.infer);
+            board.render(frame, sections[1], &columns);
+        } else if self.preview.is_some() {
+            let body = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(sections[1]);
+            self.render_table(frame, body[0]);
+            self.render_preview(frame, body[1]);
+        } else {
+            self.render_table(frame, sections[1]);
+        }
         self.render_footer(frame, sections[2]);
+        match &self.overlay {
+            Some(Overlay::NewSession(form)) => {
+                let area = dialog_rect(60, NewSessionForm::height(), frame.area());
+                form.render(frame, area);
+            }
+            Some(Overlay::Kill { dialog, .. }) => {
+                let area = dialog_rect(50, dialog.height(), frame.area());
+                dialog.render(frame, area);
+            }
+            Some(Overlay::Rename { dialog, .. } | Overlay::Context { dialog, .. }) => {
+                let area = dialog_rect(50, PromptDialog::height(), frame.area());
+                dialog.render(frame, area);
+            }
+            Some(Overlay::Status { dialog, .. }) => {
+                let area = dialog_rect(60, StatusDialog::height(), frame.area());
+                dialog.render(frame, area);
+            }
+            None => {}
+        }
+    }
+
+    fn render_preview(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(preview) = self.preview.as_mut() else {
+            return;
+        };
+        let title = match &preview.target {
+            Some((_, target)) => format!("Preview {target}"),
+            None => "Preview".to_string(),
+        };
+        // Pin the view to the bottom of the capture, like the pane itself.
+        let height = area.height.saturating_sub(2);
+        let total = u16::try_from(preview.text.lines.len()).unwrap_or(u16::MAX);
+        let max_scroll = total.saturating_sub(height);
+        preview.scroll_back = preview.scroll_back.min(max_scroll);
+        let top = max_scroll - preview.scroll_back;
+        let paragraph = Paragraph::new(preview.text.clone())
+            .scroll((top, 0))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(paragraph, area);
     }
 
     fn render_search(&self, frame: &mut Frame, area: Rect) {
@@ -359,16 +1877,50 @@ impl App {
                 "Search: ".to_string(),
                 Style::default().add_modifier(Modifier::DIM),
             )
+        } else if let Some(error) = &self.search_error {
+            (
+                format!("Search: {}  ({error})", self.search_query),
+                Style::default().fg(Color::Red),
+            )
         } else {
             (format!("Search: {}", self.search_query), Style::default())
         };
+        let mut view = format!("sort: {} {}", self.sort.label(), self.sort_order.arrow());
+        if self.servers.len() > 1 {
+            view.push_str(&format!(" · {} servers", self.servers.len()));
+        }
+        if self.attached_only {
+            view.push_str(" · attached only");
+        }
+        match &self.handoff {
+            Handoff::Switch {
+                client: Some(client),
+            } => {
+                view.push_str(&format!(" · client: {client}"));
+                if let Some(attached) = self.clients.iter().find(|other| &other.name == client) {
+                    view.push_str(&format!(" ({})", attached.session_name));
+                }
+            }
+            Handoff::Switch { client: None } => {}
+            Handoff::Attach => view.push_str(" · attach on exit"),
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let view_width = UnicodeWidthStr::width(view.as_str()) as u16;
+        let sections =
+            Layout::horizontal([Constraint::Min(1), Constraint::Length(view_width)]).split(area);
         let search = Paragraph::new(Text::from(text)).style(style);
-        frame.render_widget(search, area);
+        let view = Paragraph::new(Text::from(view))
+            .alignment(Alignment::Right)
+            .style(Style::default().add_modifier(Modifier::DIM));
+        frame.render_widget(search, sections[0]);
+        frame.render_widget(view, sections[1]);
     }
 
     fn render_table(&mut self, frame: &mut Frame, area: Rect) {
-        let header = Row::new(["Session", "Status", "Context"])
+        let header = Row::new(["Session", "Status", "Context", "Details"])
             .style(Style::default().add_modifier(Modifier::BOLD));
+        let query = crate::query::Query::parse(&self.search_query).unwrap_or_default();
+        let query = query.fuzzy();
 
         let rows = self.rows.iter().enumerate().map(|(index, item)| {
             let mut base_style = if index % 2 == 0 {
@@ -376,23 +1928,67 @@ impl App {
             } else {
                 Style::default().add_modifier(Modifier::DIM)
             };
-            if matches!(item, RowItem::Pane(_)) {
-                base_style = base_style.add_modifier(Modifier::DIM);
+            match item {
+                RowItem::Server(_) => base_style = Style::default().add_modifier(Modifier::BOLD),
+                RowItem::Pane(_) => base_style = base_style.add_modifier(Modifier::DIM),
+                RowItem::Session(_) => {}
+            }
+            let (status, status_style) = status_display(item, self.infer);
+            let marker = if self.marked.contains(&item.key()) {
+                "● "
+            } else if self.marked.is_empty() {
+                ""
+            } else {
+                "  "
+            };
+            let positions = if query.is_empty() || matches!(item, RowItem::Server(_)) {
+                Vec::new()
+            } else {
+                query
+                    .matches(&search_text(item, self.infer))
+                    .map(|found| found.positions)
+                    .unwrap_or_default()
+            };
+            let cells = [row_name(item), status, row_context(item)];
+            let mut offset = 0;
+            let cells = cells.map(|text| {
+                let line = highlight(&text, &positions, offset);
+                // Fields are joined by one tab in `search_text`.
+                offset += text.chars().count() + 1;
+                line
+            });
+            let details = row_details(item);
+            // A pane's details start with the metadata searched after its
+            // context; a session's id is searched but not shown.
+            let details = if matches!(item, RowItem::Pane(_)) {
+                highlight(&details, &positions, offset)
+            } else {
+                Line::from(details)
+            };
+            let [mut label, status, context] = cells;
+            if !marker.is_empty() {
+                label.spans.insert(0, Span::raw(marker));
+            }
+            if matches!(item, RowItem::Pane(pane) if pane.current) {
+                label.spans.push(Span::raw("*"));
             }
             Row::new(vec![
-                Cell::from(row_label(item)),
-                Cell::from(status_text(row_status(item))).style(status_style(row_status(item))),
-                Cell::from(row_context(item)),
+                Cell::from(label),
+                Cell::from(status).style(status_style),
+                Cell::from(context),
+                Cell::from(details),
             ])
             .style(base_style)
         });
 
+        let marker_width = if self.marked.is_empty() { 0 } else { 2 };
         let table = Table::new(
             rows,
             [
-                Constraint::Length(self.widths.0 + 1),
+                Constraint::Length(self.widths.0 + marker_width + 1),
                 Constraint::Length(self.widths.1 + 1),
-                Constraint::Min(self.widths.2 + 1),
+                Constraint::Length(self.widths.2 + 1),
+                Constraint::Min(self.widths.3 + 1),
             ],
         )
         .header(header)
@@ -400,12 +1996,43 @@ impl App {
         .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 
         frame.render_stateful_widget(table, area, &mut self.state);
+        if self.rows.is_empty() {
+            self.render_empty_state(frame, area);
+        }
+    }
+
+    fn render_empty_state(&self, frame: &mut Frame, area: Rect) {
+        let message = match &self.unavailable {
+            Some(TmuxError::NotInstalled) => {
+                "tmux is not installed or not on PATH.\nInstall tmux, then restart jkl.".to_string()
+            }
+            Some(error) => format!("{error}.\nPress n to start a session, or q to quit."),
+            None if self.sessions.is_empty() => {
+                "No tmux sessions.\nPress n to create one.".to_string()
+            }
+            None => "No sessions match.".to_string(),
+        };
+        let height = u16::try_from(message.lines().count()).unwrap_or(u16::MAX);
+        let inner = Layout::vertical([Constraint::Length(height)])
+            .flex(ratatui::layout::Flex::Center)
+            .split(area);
+        let paragraph = Paragraph::new(message)
+            .alignment(Alignment::Center)
+            .style(Style::default().add_modifier(Modifier::DIM));
+        frame.render_widget(paragraph, inner[0]);
     }
 
     fn render_footer(&self, frame: &mut Frame, area: Rect) {
         let sections = Layout::horizontal([Constraint::Min(1), Constraint::Length(9)]).split(area);
-        let footer = Paragraph::new(Text::from(INFO_TEXT));
-        let mode = if self.search_mode {
+        let info = if self.board.is_some() {
+            BOARD_INFO_TEXT
+        } else {
+            INFO_TEXT
+        };
+        let footer = Paragraph::new(Text::from(info));
+        let mode = if self.overlay.is_some() {
+            "[EDIT]"
+        } else if self.search_mode {
             "[SEARCH]"
         } else {
             "[NORM]"
@@ -418,6 +2045,7 @@ impl App {
 }
 
 struct PaneSelector {
+    store: ContextStore,
     session_name: String,
     pane_id: String,
     options: Vec<(String, Option<crate::context::AgentStatus>)>,
@@ -425,14 +2053,19 @@ struct PaneSelector {
 }
 
 impl PaneSelector {
-    fn new(session_name: String, pane_id: String) -> Result<Self, Box<dyn std::error::Error>> {
+    fn new(
+        store: ContextStore,
+        session_name: String,
+        pane_id: String,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let options = pane_status_options();
-        let current = current_pane_status(&session_name, &pane_id)?;
+        let current = current_pane_status(&store, &session_name, &pane_id)?;
         let selected = options
             .iter()
             .position(|(_, status)| *status == current)
             .unwrap_or(0);
         Ok(Self {
+            store,
             session_name,
             pane_id,
             options,
@@ -466,12 +2099,8 @@ impl PaneSelector {
                     }
                     KeyCode::Enter => {
                         let status = self.options[self.selected].1.clone();
-                        crate::context::upsert_pane(
-                            &self.session_name,
-                            &self.pane_id,
-                            status,
-                            None,
-                        )?;
+                        self.store
+                            .upsert_pane(&self.session_name, &self.pane_id, status, None)?;
                         return Ok(());
                     }
                     _ => {}
@@ -522,15 +2151,20 @@ fn pane_status_options() -> Vec<(String, Option<crate::context::AgentStatus>)> {
         ),
         ("idle".to_string(), Some(crate::context::AgentStatus::Idle)),
         ("done".to_string(), Some(crate::context::AgentStatus::Done)),
+        (
+            "failed".to_string(),
+            Some(crate::context::AgentStatus::Failed),
+        ),
         ("none".to_string(), Some(crate::context::AgentStatus::None)),
     ]
 }
 
 fn current_pane_status(
+    store: &ContextStore,
     session_name: &str,
     pane_id: &str,
 ) -> Result<Option<crate::context::AgentStatus>, Box<dyn std::error::Error>> {
-    let contexts = crate::context::load_contexts()?;
+    let contexts = store.load()?;
     let key = crate::context::session_key(session_name);
     let status = contexts
         .get(&key)
@@ -555,110 +2189,331 @@ fn centered_rect(percent_x: u16, percent_y: u16, rect: Rect) -> Rect {
     horizontal[1]
 }
 
+/// A box `percent_x` wide and `height` rows tall, centered in `rect`.
+fn dialog_rect(percent_x: u16, height: u16, rect: Rect) -> Rect {
+    let vertical = Layout::vertical([Constraint::Length(height.min(rect.height))])
+        .flex(ratatui::layout::Flex::Center)
+        .split(rect);
+    let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)])
+        .flex(ratatui::layout::Flex::Center)
+        .split(vertical[0]);
+    horizontal[0]
+}
+
+/// Indices of the matching `candidates`, best first.
 fn run_fzf_filter(
     query: &str,
     candidates: &[String],
-) -> Result<String, Box<dyn std::error::Error>> {
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    // Session ids repeat across servers, so lines are told apart by a
+    // leading index that fzf does not match against.
     let mut child = Command::new("fzf")
-        .args(["--filter", query, "--print-query", "--reverse"])
+        .args(["--filter", query, "--delimiter", "\t", "--nth", "2.."])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()?;
 
     if let Some(mut stdin) = child.stdin.take() {
-        for line in candidates {
-            writeln!(stdin, "{line}")?;
+        for (index, line) in candidates.iter().enumerate() {
+            // fzf may have exited already; its status and stderr say why.
+            if writeln!(stdin, "{index}\t{line}").is_err() {
+                break;
+            }
         }
     }
 
     let output = child.wait_with_output()?;
-    if !output.status.success() && output.status.code() != Some(1) {
-        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
-        return Err(Box::new(io::Error::new(io::ErrorKind::Other, message)));
+    // 1 means nothing matched and 130 that fzf was interrupted.
+    if !matches!(output.status.code(), Some(0 | 1 | 130)) {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let message = if stderr.is_empty() {
+            format!("fzf failed ({})", output.status)
+        } else {
+            format!("fzf failed ({}): {stderr}", output.status)
+        };
+        return Err(Box::new(io::Error::other(message)));
     }
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split('\t').next()?.parse().ok())
+        .collect())
 }
 
 fn build_sessions(
-    sessions: Vec<crate::tmux::TmuxSession>,
-    contexts: HashMap<String, crate::context::SessionContext>,
-    panes: Vec<crate::tmux::TmuxPane>,
+    server: usize,
+    snapshot: Snapshot,
+    contexts: &HashMap<String, crate::context::SessionContext>,
 ) -> Vec<SessionRow> {
-    let mut panes_by_session: HashMap<String, Vec<String>> = HashMap::new();
-    for pane in panes {
-        panes_by_session
-            .entry(pane.session_name)
-            .or_default()
-            .push(pane.pane_id);
-    }
-
-    sessions
+    let now = unix_now();
+    snapshot
+        .sessions
         .into_iter()
-        .map(|session| {
-            let key = crate::context::session_key(&session.name);
-            let context = contexts.get(&key);
-            let status = context.and_then(|ctx| ctx.status.clone());
-            let context_value = normalize_field(context.and_then(|ctx| ctx.context.as_ref()));
-            let mut pane_rows = panes_by_session
-                .get(&session.name)
-                .cloned()
-                .unwrap_or_default();
-            pane_rows.sort();
-            let panes = pane_rows
+        .map(|SessionSnapshot { session, windows }| {
+            let panes = windows
                 .into_iter()
-                .map(|pane_id| {
-                    let pane_status = context
-                        .and_then(|ctx| ctx.panes.get(&pane_id))
-                        .and_then(|pane| pane.status.clone());
-                    PaneRow {
-                        id: pane_id,
-                        status: pane_status,
-                        session_id: session.id.clone(),
-                    }
+                .flat_map(|window| {
+                    let label = format!("{}:{}", window.index, window.name);
+                    let (index, active) = (window.index, window.active);
+                    window
+                        .panes
+                        .into_iter()
+                        .map(move |pane| (label.clone(), index, active, pane))
                 })
-                .collect();
-            SessionRow {
+                .map(|(window, window_index, window_active, pane)| PaneRow {
+                    server,
+                    inferred: crate::infer::pane_status(&pane, now),
+                    id: pane.pane_id,
+                    status: None,
+                    context: String::new(),
+                    session_id: session.id.clone(),
+                    window_id: pane.window_id,
+                    window,
+                    window_index,
+                    pane_index: pane.pane_index,
+                    active: pane.active,
+                    window_active,
+                    current: pane.active && window_active,
+                    command: pane.current_command,
+                    path: pane.current_path,
+                    title: pane.title,
+                    pid: pane.pid,
+                    activity: pane.activity,
+                    updated: None,
+                })
+                .collect::<Vec<PaneRow>>();
+            let inferred = crate::infer::session_status(
+                panes.iter().filter_map(|pane| pane.inferred.as_ref()),
+            );
+            let key = crate::context::session_key(&session.name);
+            let mut row = SessionRow {
+                server,
                 id: session.id,
                 name: session.name,
-                status,
-                context: context_value,
+                status: None,
+                inferred,
+                context: String::new(),
+                attached: session.attached,
+                windows: session.windows,
+                created: session.created,
+                activity: session.activity,
+                last_attached: session.last_attached,
+                seen: None,
+                updated: None,
                 panes,
-            }
+            };
+            row.apply_context(contexts.get(&key));
+            row
         })
         .collect()
 }
 
-fn collect_live_panes(panes: &[crate::tmux::TmuxPane]) -> HashMap<String, HashSet<String>> {
-    let mut live = HashMap::new();
-    for pane in panes {
-        live.entry(pane.session_name.clone())
-            .or_insert_with(HashSet::new)
-            .insert(pane.pane_id.clone());
+fn row_label(item: &RowItem) -> String {
+    match item {
+        RowItem::Pane(row) if row.current => format!("{}*", row_name(item)),
+        item => row_name(item),
     }
-    live
 }
 
-fn row_label(item: &RowItem) -> String {
+/// The label without the `*` that follows clients around.
+fn row_name(item: &RowItem) -> String {
     match item {
+        RowItem::Server(row) => format!("[{}]", row.label),
         RowItem::Session(row) => row.name.clone(),
-        RowItem::Pane(row) => format!("  └─ {}", row.id),
+        RowItem::Pane(row) => format!("  └─ {} {}", row.id, row.window),
     }
 }
 
 fn row_status(item: &RowItem) -> Option<&crate::context::AgentStatus> {
     match item {
+        RowItem::Server(_) => None,
         RowItem::Session(row) => row.status.as_ref(),
         RowItem::Pane(row) => row.status.as_ref(),
     }
 }
 
+fn row_inferred(item: &RowItem) -> Option<&crate::context::AgentStatus> {
+    match item {
+        RowItem::Server(_) => None,
+        RowItem::Session(row) => row.inferred.as_ref(),
+        RowItem::Pane(row) => row.inferred.as_ref(),
+    }
+}
+
+/// The reported status, with the inferred one alongside when they disagree
+/// or in its place (marked `~`) when nothing was reported.
+fn status_display(item: &RowItem, infer: bool) -> (String, Style) {
+    let reported = row_status(item);
+    let inferred = if infer { row_inferred(item) } else { None };
+    match (reported, inferred) {
+        (Some(reported), Some(inferred)) if reported != inferred => (
+            format!("{reported} (~{inferred})"),
+            status_style(Some(reported)),
+        ),
+        (None, Some(inferred)) => (
+            format!("~{inferred}"),
+            status_style(Some(inferred)).add_modifier(Modifier::ITALIC),
+        ),
+        _ => (status_text(reported), status_style(reported)),
+    }
+}
+
 fn row_context(item: &RowItem) -> String {
     match item {
+        RowItem::Server(_) => String::new(),
         RowItem::Session(row) => row.context.clone(),
-        RowItem::Pane(_) => DATA_NOT_RECEIVED.to_string(),
+        RowItem::Pane(row) => row.context.clone(),
+    }
+}
+
+/// What search matches a row against: its name, status and context cells,
+/// then a pane's command, path and title, joined by tabs, then ids that are
+/// not displayed. Ages and attached counts change on every refresh and are
+/// left out, so results hold still.
+fn search_text(item: &RowItem, infer: bool) -> String {
+    let mut fields = vec![
+        row_name(item),
+        status_display(item, infer).0,
+        row_context(item),
+    ];
+    match item {
+        RowItem::Session(row) => fields.push(row.id.clone()),
+        RowItem::Pane(row) => fields.push(pane_metadata(row).join(" · ")),
+        RowItem::Server(_) => {}
+    }
+    fields.join("\t")
+}
+
+/// `text` with the characters at `positions` emphasized, where positions
+/// count from `offset` characters before the start of `text`.
+fn highlight(text: &str, positions: &[usize], offset: usize) -> Line<'static> {
+    let style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut current = String::new();
+    let mut current_matched = false;
+    for (index, c) in text.chars().enumerate() {
+        let matched = positions.binary_search(&(offset + index)).is_ok();
+        if matched != current_matched && !current.is_empty() {
+            let chunk = std::mem::take(&mut current);
+            spans.push(if current_matched {
+                Span::styled(chunk, style)
+            } else {
+                Span::raw(chunk)
+            });
+        }
+        current_matched = matched;
+        current.push(c);
+    }
+    if !current.is_empty() {
+        spans.push(if current_matched {
+            Span::styled(current, style)
+        } else {
+            Span::raw(current)
+        });
+    }
+    Line::from(spans)
+}
+
+fn row_details(item: &RowItem) -> String {
+    match item {
+        RowItem::Server(row) => match row.sessions {
+            1 => "1 session".to_string(),
+            count => format!("{count} sessions"),
+        },
+        RowItem::Session(row) => {
+            let now = unix_now();
+            let mut parts = Vec::new();
+            parts.push(match row.attached {
+                0 => match row.last_attached {
+                    Some(last) => format!("detached (last {})", format_age(last, now)),
+                    None => "detached".to_string(),
+                },
+                1 => "attached".to_string(),
+                count => format!("attached ×{count}"),
+            });
+            if row.windows > 0 {
+                let noun = if row.windows == 1 {
+                    "window"
+                } else {
+                    "windows"
+                };
+                parts.push(format!("{} {noun}", row.windows));
+            }
+            if let Some(activity) = row.activity {
+                parts.push(format!("active {}", format_age(activity, now)));
+            }
+            if let Some(seen) = row.seen {
+                parts.push(format!("seen {}", format_age(seen, now)));
+            }
+            parts.join(" · ")
+        }
+        RowItem::Pane(row) => {
+            let mut parts = pane_metadata(row);
+            if let Some(pid) = row.pid {
+                parts.push(format!("pid {pid}"));
+            }
+            if let Some(activity) = row.activity {
+                parts.push(format_age(activity, unix_now()));
+            }
+            if parts.is_empty() {
+                DATA_NOT_RECEIVED.to_string()
+            } else {
+                parts.join(" · ")
+            }
+        }
+    }
+}
+
+/// The start of a pane's details that only changes with what runs in it.
+fn pane_metadata(row: &PaneRow) -> Vec<String> {
+    let mut parts = Vec::new();
+    if !row.command.is_empty() {
+        parts.push(row.command.clone());
+    }
+    if !row.path.is_empty() {
+        parts.push(shorten_home(&row.path));
+    }
+    if !row.title.is_empty() && row.title != row.command {
+        parts.push(format!("\"{}\"", row.title));
+    }
+    parts
+}
+
+fn shorten_home(path: &str) -> String {
+    match std::env::var("HOME") {
+        Ok(home) if !home.is_empty() && path.starts_with(&home) => {
+            format!("~{}", &path[home.len()..])
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn format_age(timestamp: u64, now: u64) -> String {
+    let elapsed = now.saturating_sub(timestamp);
+    match elapsed {
+        0..60 => format!("{elapsed}s ago"),
+        60..3600 => format!("{}m ago", elapsed / 60),
+        3600..86400 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86400),
     }
 }
 
+/// The status field filters see: the reported one, else the suggestion
+/// when inference is on.
+fn filter_status<'a>(
+    status: Option<&'a crate::context::AgentStatus>,
+    inferred: Option<&'a crate::context::AgentStatus>,
+    infer: bool,
+) -> Option<&'a crate::context::AgentStatus> {
+    status.or(inferred.filter(|_| infer))
+}
+
+fn filter_context(context: &str) -> Option<&str> {
+    (context != DATA_NOT_RECEIVED).then_some(context)
+}
+
 fn normalize_field(value: Option<&String>) -> String {
     value
         .map(|value| value.trim())
@@ -676,6 +2531,7 @@ fn status_text(status: Option<&crate::context::AgentStatus>) -> String {
 fn status_style(status: Option<&crate::context::AgentStatus>) -> Style {
     match status {
         Some(crate::context::AgentStatus::Done) => Style::default().fg(Color::Green),
+        Some(crate::context::AgentStatus::Failed) => Style::default().fg(Color::Red),
         Some(crate::context::AgentStatus::None) => Style::default().fg(Color::Gray),
         Some(crate::context::AgentStatus::Working) => Style::default().fg(Color::Blue),
         Some(crate::context::AgentStatus::Waiting | crate::context::AgentStatus::Idle) => {
@@ -685,7 +2541,7 @@ fn status_style(status: Option<&crate::context::AgentStatus>) -> Style {
     }
 }
 
-fn measure_widths(items: &[RowItem]) -> (u16, u16, u16) {
+fn measure_widths(items: &[RowItem], infer: bool) -> (u16, u16, u16, u16) {
     let name_len = items
         .iter()
         .map(|item| UnicodeWidthStr::width(row_label(item).as_str()))
@@ -694,7 +2550,7 @@ fn measure_widths(items: &[RowItem]) -> (u16, u16, u16) {
         .max(UnicodeWidthStr::width("Session"));
     let status_len = items
         .iter()
-        .map(|item| UnicodeWidthStr::width(status_text(row_status(item)).as_str()))
+        .map(|item| UnicodeWidthStr::width(status_display(item, infer).0.as_str()))
         .max()
         .unwrap_or(0)
         .max(UnicodeWidthStr::width("Status"));
@@ -704,7 +2560,969 @@ fn measure_widths(items: &[RowItem]) -> (u16, u16, u16) {
         .max()
         .unwrap_or(0)
         .max(UnicodeWidthStr::width("Context"));
+    let details_len = items
+        .iter()
+        .map(|item| UnicodeWidthStr::width(row_details(item).as_str()))
+        .max()
+        .unwrap_or(0)
+        .max(UnicodeWidthStr::width("Details"));
 
     #[allow(clippy::cast_possible_truncation)]
-    (name_len as u16, status_len as u16, context_len as u16)
+    (
+        name_len as u16,
+        status_len as u16,
+        context_len as u16,
+        details_len as u16,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::AgentStatus;
+    use crate::tmux::fake::FakeTmux;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn press(app: &mut App, code: KeyCode) -> Flow {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap()
+    }
+
+    fn render(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content
+            .chunks(width)
+            .map(|line| line.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn fixture() -> FakeTmux {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.add_session("$2", "web");
+        tmux.add_pane("api", "%1");
+        tmux.add_pane("api", "%2");
+        tmux.add_pane("web", "%3");
+        tmux
+    }
+
+    fn selected_label(app: &App) -> String {
+        app.selected_row().map(row_label).unwrap_or_default()
+    }
+
+    #[test]
+    fn renders_sessions_with_stored_status() {
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "api".to_string(),
+                None,
+                Some(AgentStatus::Working),
+                Some("flaky test".to_string()),
+            )
+            .unwrap();
+        let mut app = App::load(&tmux, store).unwrap();
+
+        let screen = render(&mut app);
+        assert!(screen.contains("api"));
+        assert!(screen.contains("working"));
+        assert!(screen.contains("flaky test"));
+        assert!(screen.contains("web"));
+        assert!(screen.contains("[NORM]"));
+    }
+
+    #[test]
+    fn pane_rows_show_tmux_details() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.push_pane(crate::tmux::TmuxPane {
+            session_name: "api".to_string(),
+            pane_id: "%7".to_string(),
+            window_index: 2,
+            window_name: "agent".to_string(),
+            active: true,
+            window_active: true,
+            pid: Some(4242),
+            current_command: "claude".to_string(),
+            current_path: "/srv/api".to_string(),
+            title: "fixing tests".to_string(),
+            ..Default::default()
+        });
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        press(&mut app, KeyCode::Char('l'));
+
+        let screen = render(&mut app);
+        assert!(screen.contains("└─ %7 2:agent*"));
+        assert!(screen.contains("claude · /srv/api · \"fixing tests\" · pid 4242"));
+    }
+
+    #[test]
+    fn sort_and_attached_filter_reorder_sessions() {
+        let tmux = FakeTmux::new();
+        for (id, name, attached, activity) in [
+            ("$1", "old", 0, 100),
+            ("$2", "busy", 1, 300),
+            ("$3", "recent", 0, 400),
+        ] {
+            tmux.push_session(crate::tmux::TmuxSession {
+                id: id.to_string(),
+                name: name.to_string(),
+                attached,
+                activity: Some(activity),
+                ..Default::default()
+            });
+        }
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        let names = |app: &App| app.rows.iter().map(row_label).collect::<Vec<_>>().join(",");
+        assert_eq!(names(&app), "old,busy,recent");
+
+        press(&mut app, KeyCode::Char('o'));
+        assert_eq!(app.sort, SessionSort::Activity);
+        assert_eq!(names(&app), "recent,busy,old");
+        assert!(render(&mut app).contains("sort: activity"));
+
+        press(&mut app, KeyCode::Char('o'));
+        assert_eq!(names(&app), "busy,recent,old");
+
+        press(&mut app, KeyCode::Char('a'));
+        assert_eq!(names(&app), "busy");
+        assert!(render(&mut app).contains("attached only"));
+    }
+
+    #[test]
+    fn sort_modes_order_panes_toggle_and_persist() {
+        use crate::context::AgentStatus;
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        store
+            .upsert_pane("api", "%2", Some(AgentStatus::Failed), None)
+            .unwrap();
+        store
+            .upsert_pane("api", "%1", Some(AgentStatus::Working), None)
+            .unwrap();
+        store
+            .upsert_session("web".to_string(), None, Some(AgentStatus::Waiting), None)
+            .unwrap();
+        let mut app = App::load(&tmux, store.clone()).unwrap();
+        app.expanded_sessions.insert((0, "$1".to_string()));
+        let names = |app: &App| app.rows.iter().map(row_label).collect::<Vec<_>>().join(",");
+
+        while app.sort != SessionSort::Status {
+            press(&mut app, KeyCode::Char('o'));
+        }
+        // api has a failed pane, which beats web's waiting.
+        assert_eq!(names(&app), "api,  └─ %2 0:,  └─ %1 0:,web");
+        assert!(render(&mut app).contains("sort: status ↑"));
+
+        press(&mut app, KeyCode::Char('O'));
+        assert_eq!(app.sort_order, SortOrder::Descending);
+        assert_eq!(names(&app), "web,api,  └─ %1 0:,  └─ %2 0:");
+
+        press(&mut app, KeyCode::Char('o'));
+        assert_eq!(app.sort, SessionSort::Updated);
+        assert_eq!(app.sort_order, SortOrder::Descending);
+
+        press(&mut app, KeyCode::Char('o'));
+        assert_eq!(app.sort, SessionSort::Waiting);
+        assert_eq!(names(&app), "web,api,  └─ %1 0:,  └─ %2 0:");
+
+        // The next launch starts where this one left off.
+        press(&mut app, KeyCode::Char('O'));
+        let app = App::load(&tmux, store).unwrap();
+        assert_eq!(app.sort, SessionSort::Waiting);
+        assert_eq!(app.sort_order, SortOrder::Descending);
+    }
+
+    #[test]
+    fn name_sort_orders_panes_by_window_then_pane_index() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        for (pane_id, window_index, pane_index) in [("%1", 10, 0), ("%2", 2, 1), ("%3", 2, 0)] {
+            tmux.push_pane(crate::tmux::TmuxPane {
+                session_id: "$1".to_string(),
+                session_name: "api".to_string(),
+                pane_id: pane_id.to_string(),
+                window_id: format!("@{window_index}"),
+                window_index,
+                pane_index,
+                ..Default::default()
+            });
+        }
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        app.expanded_sessions.insert((0, "$1".to_string()));
+        while app.sort != SessionSort::Name {
+            press(&mut app, KeyCode::Char('o'));
+        }
+
+        let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+        assert_eq!(labels, ["api", "  └─ %3 2:", "  └─ %2 2:", "  └─ %1 10:"]);
+    }
+
+    #[test]
+    fn preview_follows_selection_and_scrolls() {
+        let tmux = fixture();
+        tmux.set_output("$1", "api prompt\n\x1b[32m$ \x1b[0mcargo test");
+        let lines = (1..=30)
+            .map(|line| format!("line {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        tmux.set_output("%1", &lines);
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+
+        press(&mut app, KeyCode::Char('p'));
+        let screen = render(&mut app);
+        assert!(screen.contains("Preview $1"));
+        assert!(screen.contains("$ cargo test"));
+
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Char('j'));
+        app.update_preview();
+        let screen = render(&mut app);
+        assert!(screen.contains("Preview %1"));
+        assert!(screen.contains("line 30"));
+
+        press(&mut app, KeyCode::PageUp);
+        let screen = render(&mut app);
+        assert!(!screen.contains("line 30"));
+        assert!(screen.contains("line 20"));
+
+        press(&mut app, KeyCode::Char('p'));
+        assert!(!render(&mut app).contains("Preview"));
+    }
+
+    #[test]
+    fn inferred_status_fills_in_missing_reports() {
+        let tmux = FakeTmux::new();
+        tmux.add_session("$1", "api");
+        tmux.push_pane(crate::tmux::TmuxPane {
+            session_id: "$1".to_string(),
+            session_name: "api".to_string(),
+            pane_id: "%1".to_string(),
+            bell: true,
+            ..Default::default()
+        });
+        tmux.push_pane(crate::tmux::TmuxPane {
+            session_id: "$1".to_string(),
+            session_name: "api".to_string(),
+            pane_id: "%2".to_string(),
+            pane_index: 1,
+            dead: true,
+            dead_status: Some(0),
+            ..Default::default()
+        });
+        let store = ContextStore::temp();
+        store
+            .upsert_pane("api", "%2", Some(AgentStatus::Working), None)
+            .unwrap();
+        let mut app = App::load(&tmux, store).unwrap();
+        press(&mut app, KeyCode::Char('l'));
+        assert!(!render(&mut app).contains("~waiting"));
+
+        press(&mut app, KeyCode::Char('i'));
+        let screen = render(&mut app);
+        assert!(screen.contains("~waiting"));
+        assert!(screen.contains("working (~done)"));
+    }
+
+    #[test]
+    fn formats_activity_age() {
+        assert_eq!(format_age(100, 130), "30s ago");
+        assert_eq!(format_age(100, 100 + 5 * 60), "5m ago");
+        assert_eq!(format_age(100, 100 + 3 * 3600), "3h ago");
+        assert_eq!(format_age(200, 100), "0s ago");
+    }
+
+    #[test]
+    fn expand_and_collapse_toggle_pane_rows() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        assert_eq!(app.rows.len(), 2);
+
+        press(&mut app, KeyCode::Char('l'));
+        assert_eq!(app.rows.len(), 4);
+        assert!(render(&mut app).contains("└─ %2 0:"));
+
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_label(&app), "  └─ %1 0:");
+        press(&mut app, KeyCode::Char('h'));
+        assert_eq!(app.rows.len(), 2);
+        assert_eq!(selected_label(&app), "api");
+    }
+
+    #[test]
+    fn refresh_prunes_dead_panes_and_picks_up_sessions() {
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        store
+            .upsert_pane("api", "%2", Some(AgentStatus::Waiting), None)
+            .unwrap();
+        let mut app = App::load(&tmux, store.clone()).unwrap();
+
+        tmux.remove_pane("%2");
+        tmux.add_session("$3", "docs");
+        tmux.add_pane("docs", "%4");
+        press(&mut app, KeyCode::Char('r'));
+
+        assert_eq!(app.rows.len(), 3);
+        assert!(render(&mut app).contains("docs"));
+        let contexts = store.load().unwrap();
+        let api = &contexts[&crate::context::session_key("api")];
+        assert!(!api.panes.contains_key("%2"));
+    }
+
+    #[test]
+    fn board_moves_cards_between_status_columns() {
+        let tmux = fixture();
+        tmux.add_session("$3", "docs");
+        tmux.add_pane("docs", "%4");
+        let store = ContextStore::temp();
+        store
+            .upsert_pane(
+                "api",
+                "%2",
+                Some(AgentStatus::Waiting),
+                Some("review".to_string()),
+            )
+            .unwrap();
+        store
+            .upsert_session("web".to_string(), None, Some(AgentStatus::Working), None)
+            .unwrap();
+        let mut app = App::load(&tmux, store.clone()).unwrap();
+        let selected = |app: &App| {
+            let board = app.board.as_ref().unwrap();
+            let columns = board::columns(&app.filtered_sessions, app.infer);
+            board.selected(&columns).map(|card| card.item.key())
+        };
+        let pane = RowKey::Pane {
+            server: 0,
+            session_id: "$1".to_string(),
+            pane_id: "%2".to_string(),
+        };
+
+        press(&mut app, KeyCode::Char('v'));
+        let screen = render(&mut app);
+        assert!(screen.contains("working (1)"));
+        assert!(screen.contains("api %2 0:"));
+        // Sessions without a status are listed too.
+        assert!(screen.contains("none (1)"));
+        assert!(screen.contains("docs"));
+        assert!(screen.contains("review"));
+
+        press(&mut app, KeyCode::Char('l'));
+        assert!(selected(&app) == Some(pane.clone()));
+        press(&mut app, KeyCode::Char('L'));
+        assert_eq!(app.board.as_ref().unwrap().column, 2);
+        assert!(selected(&app) == Some(pane.clone()));
+        let contexts = store.load().unwrap();
+        let api = &contexts[&crate::context::session_key("api")];
+        assert_eq!(api.panes["%2"].status, Some(AgentStatus::Idle));
+        assert_eq!(
+            tmux.option("%2", crate::mirror::STATUS_OPTION).as_deref(),
+            Some("idle")
+        );
+
+        press(&mut app, KeyCode::Char('H'));
+        press(&mut app, KeyCode::Char('H'));
+        assert!(render(&mut app).contains("working (2)"));
+        press(&mut app, KeyCode::Char('j'));
+        // Table actions stay out of the board.
+        press(&mut app, KeyCode::Char('x'));
+        assert!(app.overlay.is_none());
+        assert_eq!(press(&mut app, KeyCode::Enter), Flow::Exit);
+        assert_eq!(tmux.switched(), ["$2"]);
+        // Enter while searching also takes the card, not the hidden table row.
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!(press(&mut app, KeyCode::Enter), Flow::Exit);
+        assert_eq!(tmux.switched(), ["$2", "$2"]);
+
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('v'));
+        assert!(app.board.is_none());
+    }
+
+    #[test]
+    fn edits_status_and_context_in_place() {
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        let mut app = App::load(&tmux, store.clone()).unwrap();
+        let stored = |pane: Option<&str>| {
+            let contexts = store.load().unwrap();
+            let api = contexts[&crate::context::session_key("api")].clone();
+            match pane {
+                Some(pane) => (
+                    api.panes[pane].status.clone(),
+                    api.panes[pane].context.clone(),
+                ),
+                None => (api.status, api.context),
+            }
+        };
+
+        press(&mut app, KeyCode::Char('s'));
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(stored(None).0, Some(AgentStatus::Waiting));
+        assert_eq!(selected_label(&app), "api");
+        assert_eq!(
+            tmux.option("=api:", crate::mirror::STATUS_OPTION)
+                .as_deref(),
+            Some("waiting")
+        );
+
+        press(&mut app, KeyCode::Char('S'));
+        assert!(render(&mut app).contains("Status of api"));
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Enter);
+        assert!(app.overlay.is_none());
+        assert_eq!(stored(None).0, Some(AgentStatus::Working));
+
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('e'));
+        assert!(render(&mut app).contains("Context of %1 in api"));
+        type_text(&mut app, "tst");
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Left);
+        type_text(&mut app, "e");
+        press(&mut app, KeyCode::Home);
+        app.handle_paste("flaky ").unwrap();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(stored(Some("%1")).1.as_deref(), Some("flaky test"));
+        assert!(render(&mut app).contains("flaky test"));
+
+        // Editing again starts from the stored context.
+        press(&mut app, KeyCode::Char('e'));
+        press(&mut app, KeyCode::Char('u'));
+        assert!(render(&mut app).contains("flaky testu"));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(stored(Some("%1")).1.as_deref(), Some("flaky test"));
+
+        // Emptying the field clears the context and its tmux option.
+        press(&mut app, KeyCode::Char('e'));
+        app.handle_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL))
+            .unwrap();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(stored(Some("%1")).1, None);
+        assert_eq!(tmux.option("%1", crate::mirror::CONTEXT_OPTION), None);
+    }
+
+    #[test]
+    fn enter_switches_to_selected_session() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+
+        press(&mut app, KeyCode::Down);
+        assert_eq!(press(&mut app, KeyCode::Enter), Flow::Exit);
+        assert_eq!(tmux.switched(), vec!["$2".to_string()]);
+    }
+
+    #[test]
+    fn enter_on_pane_row_jumps_to_the_pane() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            tmux.calls(),
+            vec!["select-window @2", "select-pane %2", "switch-client $1"]
+        );
+    }
+
+    #[test]
+    fn enter_outside_tmux_defers_attach() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        app.handoff = Handoff::Attach;
+
+        press(&mut app, KeyCode::Down);
+        assert_eq!(press(&mut app, KeyCode::Enter), Flow::Exit);
+        assert!(tmux.calls().is_empty());
+        assert_eq!(app.pending_attach, Some((0, Target::session("$2"))));
+    }
+
+    #[test]
+    fn client_key_cycles_attached_clients() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        tmux.add_client("/dev/pts/1", "api");
+        tmux.add_client("/dev/pts/2", "web");
+        app.clients = tmux.list_clients().unwrap();
+
+        press(&mut app, KeyCode::Char('c'));
+        press(&mut app, KeyCode::Char('c'));
+        assert!(render(&mut app).contains("client: /dev/pts/2 (web)"));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.switched(), vec!["-c /dev/pts/2 $1".to_string()]);
+
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(app.handoff, Handoff::Switch { client: None });
+    }
+
+    #[test]
+    fn control_events_update_rows() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        app.servers[0].control = tmux.subscribe().ok();
+
+        tmux.add_session("$3", "docs");
+        tmux.add_pane("docs", "%4");
+        tmux.emit(ControlEvent::SessionsChanged);
+        app.process_control_events().unwrap();
+        assert_eq!(app.rows.len(), 3);
+
+        // The session-renamed hook has moved the entry by now.
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "frontend".to_string(),
+                Some("$2".to_string()),
+                Some(AgentStatus::Waiting),
+                Some("review".to_string()),
+            )
+            .unwrap();
+        app.store = store;
+        tmux.rename_session("$2", "frontend");
+        tmux.emit(ControlEvent::SessionRenamed {
+            session_id: "$2".to_string(),
+            name: "frontend".to_string(),
+        });
+        app.process_control_events().unwrap();
+        let frontend = app.sessions.iter().find(|row| row.id == "$2").unwrap();
+        assert_eq!(frontend.name, "frontend");
+        assert_eq!(frontend.status, Some(AgentStatus::Waiting));
+        assert_eq!(frontend.context, "review");
+
+        // Changes the rows carry are applied in place, without a reload
+        // that would pick up this session.
+        tmux.add_session("$9", "unannounced");
+        tmux.emit(ControlEvent::WindowRenamed {
+            window_id: "@3".to_string(),
+            name: "editor".to_string(),
+        });
+        tmux.emit(ControlEvent::WindowPaneChanged {
+            window_id: "@1".to_string(),
+            pane_id: "%1".to_string(),
+        });
+        tmux.emit(ControlEvent::SessionWindowChanged {
+            session_id: "$1".to_string(),
+            window_id: "@1".to_string(),
+        });
+        tmux.remove_pane("%2");
+        tmux.push_pane(crate::tmux::TmuxPane {
+            session_id: "$1".to_string(),
+            session_name: "api".to_string(),
+            pane_id: "%2".to_string(),
+            window_id: "@2".to_string(),
+            current_command: "vim".to_string(),
+            ..Default::default()
+        });
+        tmux.emit(ControlEvent::PaneModeChanged {
+            pane_id: "%2".to_string(),
+        });
+        app.process_control_events().unwrap();
+        assert_eq!(app.sessions.len(), 3);
+        let pane = |app: &App, id: &str| {
+            app.sessions
+                .iter()
+                .flat_map(|session| &session.panes)
+                .find(|pane| pane.id == id)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(pane(&app, "%3").window, "0:editor");
+        assert!(pane(&app, "%1").current);
+        assert_eq!(pane(&app, "%2").command, "vim");
+
+        tmux.emit(ControlEvent::SessionWindowChanged {
+            session_id: "$1".to_string(),
+            window_id: "@2".to_string(),
+        });
+        app.process_control_events().unwrap();
+        assert!(!pane(&app, "%1").current);
+
+        // A control client that goes away is reconnected after a delay, and
+        // its server reloaded for what it missed.
+        tmux.emit(ControlEvent::Exit);
+        app.process_control_events().unwrap();
+        assert!(app.servers[0].control.is_none());
+        assert!(app.servers[0].reconnect_at.is_some());
+        app.servers[0].reconnect_at = Some(Instant::now());
+        app.process_control_events().unwrap();
+        assert!(app.servers[0].control.is_some());
+        assert!(app.servers[0].reconnect_at.is_none());
+        assert_eq!(app.sessions.len(), 4);
+    }
+
+    #[test]
+    fn sessions_of_several_servers_are_grouped_by_server() {
+        let home = fixture();
+        let other = FakeTmux::new();
+        other.add_session("$1", "client-b");
+        other.add_pane("client-b", "%1");
+        let gone = FakeTmux::new();
+        gone.stop_server();
+        let servers = vec![
+            Server::new(TmuxSocket::Name("home".to_string()), &home),
+            Server::new(TmuxSocket::Name("gone".to_string()), &gone),
+            Server::new(TmuxSocket::Path("/tmp/tmux-1000/clientB".into()), &other),
+        ];
+        let mut app = App::load_servers(&home, servers, ContextStore::temp()).unwrap();
+        app.servers[0].current = true;
+        let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+        assert_eq!(labels, ["[home]", "api", "web", "[clientB]", "client-b"]);
+        assert_eq!(selected_label(&app), "api");
+
+        // Server rows are skipped, and the same session id on two servers
+        // stays two sessions.
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(selected_label(&app), "client-b");
+        press(&mut app, KeyCode::Down);
+        assert_eq!(selected_label(&app), "api");
+        press(&mut app, KeyCode::Up);
+        assert_eq!(selected_label(&app), "client-b");
+
+        // A client cannot switch servers, so it reattaches instead.
+        press(&mut app, KeyCode::Enter);
+        assert!(other.calls().is_empty());
+        assert_eq!(
+            home.calls(),
+            ["detach-client -E tmux -S /tmp/tmux-1000/clientB attach-session -t '$1'"]
+        );
+
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(home.switched(), ["$1"]);
+
+        // The preview captures `$1` from the server the row belongs to.
+        home.set_output("$1", "home prompt");
+        other.set_output("$1", "client b prompt");
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Char('p'));
+        let screen = render(&mut app);
+        assert!(screen.contains("client b prompt"));
+        assert!(!screen.contains("home prompt"));
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn new_session_form_creates_session_with_metadata() {
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        let mut app = App::load(&tmux, store.clone()).unwrap();
+
+        press(&mut app, KeyCode::Char('n'));
+        type_text(&mut app, "api");
+        press(&mut app, KeyCode::Enter);
+        assert!(render(&mut app).contains("duplicate session"));
+
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "agent");
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Tab);
+        }
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Tab);
+        type_text(&mut app, "fix login");
+        press(&mut app, KeyCode::Enter);
+
+        assert!(app.overlay.is_none());
+        assert_eq!(selected_label(&app), "agent");
+        let session_id = tmux
+            .snapshot()
+            .unwrap()
+            .sessions()
+            .find(|session| session.name == "agent")
+            .unwrap()
+            .id
+            .clone();
+        let entry = &store.load().unwrap()[&crate::context::session_key("agent")];
+        assert_eq!(entry.session_id.as_deref(), Some(session_id.as_str()));
+        assert_eq!(entry.status, Some(AgentStatus::Working));
+        assert_eq!(entry.context.as_deref(), Some("fix login"));
+
+        press(&mut app, KeyCode::Char('n'));
+        press(&mut app, KeyCode::Esc);
+        assert!(app.overlay.is_none());
+        assert_eq!(press(&mut app, KeyCode::Esc), Flow::Exit);
+    }
+
+    #[test]
+    fn kill_asks_for_confirmation_and_handles_marked_rows() {
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        store
+            .upsert_session("web".to_string(), Some("$2".to_string()), None, None)
+            .unwrap();
+        store
+            .upsert_pane("api", "%2", Some(AgentStatus::Done), None)
+            .unwrap();
+        let mut app = App::load(&tmux, store.clone()).unwrap();
+
+        press(&mut app, KeyCode::Char('x'));
+        assert!(render(&mut app).contains("session api"));
+        press(&mut app, KeyCode::Char('n'));
+        assert!(app.overlay.is_none());
+        assert!(tmux.calls().is_empty());
+
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(selected_label(&app), "web");
+        press(&mut app, KeyCode::Char(' '));
+        assert!(render(&mut app).contains("● web"));
+        press(&mut app, KeyCode::Char('x'));
+        let screen = render(&mut app);
+        assert!(screen.contains("Kill 2?"));
+        assert!(screen.contains("pane %2 in api"));
+        press(&mut app, KeyCode::Char('y'));
+
+        assert!(app.overlay.is_none());
+        assert!(app.marked.is_empty());
+        assert_eq!(tmux.calls(), vec!["kill-pane %2", "kill-session $2"]);
+        let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["api", "  └─ %1 0:"]);
+        let contexts = store.load().unwrap();
+        assert!(
+            contexts[&crate::context::session_key("api")]
+                .panes
+                .is_empty()
+        );
+        assert!(!contexts.contains_key(&crate::context::session_key("web")));
+    }
+
+    #[test]
+    fn rename_updates_tmux_and_moves_metadata() {
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        store
+            .upsert_session(
+                "api".to_string(),
+                Some("$1".to_string()),
+                None,
+                Some("auth".to_string()),
+            )
+            .unwrap();
+        let mut app = App::load(&tmux, store.clone()).unwrap();
+
+        press(&mut app, KeyCode::Char('R'));
+        assert!(render(&mut app).contains("Rename api"));
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "web");
+        press(&mut app, KeyCode::Enter);
+        assert!(render(&mut app).contains("duplicate session"));
+
+        type_text(&mut app, "-2");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.overlay.is_none());
+        assert_eq!(selected_label(&app), "web-2");
+        assert_eq!(tmux.calls(), vec!["rename-session $1"]);
+        let contexts = store.load().unwrap();
+        assert_eq!(
+            contexts[&crate::context::session_key("web-2")]
+                .context
+                .as_deref(),
+            Some("auth")
+        );
+    }
+
+    #[test]
+    fn missing_server_shows_empty_state_until_a_session_is_created() {
+        let tmux = FakeTmux::new();
+        tmux.stop_server();
+        let store = ContextStore::temp();
+        let mut app = App::load(&tmux, store).unwrap();
+
+        let screen = render(&mut app);
+        assert!(screen.contains("no tmux server running"));
+        assert!(screen.contains("Press n to start a session"));
+        press(&mut app, KeyCode::Char('r'));
+
+        press(&mut app, KeyCode::Char('n'));
+        type_text(&mut app, "first");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.unavailable.is_none());
+        assert_eq!(selected_label(&app), "first");
+    }
+
+    #[test]
+    fn search_mode_enters_and_exits() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+
+        press(&mut app, KeyCode::Char('/'));
+        assert!(app.search_mode);
+        assert!(render(&mut app).contains("[SEARCH]"));
+        assert_eq!(app.rows.len(), 2);
+
+        // `q` is a query character while searching, not quit.
+        assert_eq!(press(&mut app, KeyCode::Esc), Flow::Continue);
+        assert!(!app.search_mode);
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Flow::Exit);
+    }
+
+    #[test]
+    fn search_filters_sessions_in_process() {
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+
+        press(&mut app, KeyCode::Char('/'));
+        for c in "web".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert!(render(&mut app).contains("Search: web"));
+        assert_eq!(app.rows.len(), 1);
+        assert_eq!(selected_label(&app), "web");
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.switched(), vec!["$2".to_string()]);
+    }
+
+    #[test]
+    fn search_supports_extended_syntax() {
+        let tmux = fixture();
+        tmux.add_session("$3", "api-docs");
+        tmux.add_pane("api-docs", "%4");
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "^api !docs");
+        assert_eq!(app.rows.iter().map(row_label).collect::<Vec<_>>(), ["api"]);
+
+        app.search_query = "^web | docs".to_string();
+        app.apply_search().unwrap();
+        assert_eq!(app.rows.len(), 2);
+    }
+
+    #[test]
+    fn search_matches_panes_and_highlights_matches() {
+        let tmux = fixture();
+        tmux.push_pane(crate::tmux::TmuxPane {
+            session_id: "$2".to_string(),
+            session_name: "web".to_string(),
+            pane_id: "%4".to_string(),
+            window_id: "@4".to_string(),
+            current_command: "cargo".to_string(),
+            current_path: "/srv/web".to_string(),
+            ..crate::tmux::TmuxPane::default()
+        });
+        let store = ContextStore::temp();
+        store
+            .upsert_pane("api", "%2", None, Some("needs review".to_string()))
+            .unwrap();
+        let mut app = App::load(&tmux, store).unwrap();
+
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "review");
+        let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+        assert_eq!(labels, ["api", "  └─ %2 0:"]);
+        assert!(render(&mut app).contains("needs review"));
+
+        app.search_query = "%3".to_string();
+        app.apply_search().unwrap();
+        let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+        assert_eq!(labels, ["web", "  └─ %3 0:"]);
+
+        // A pane's command and path match; ages and attached counts do not.
+        for query in ["cargo", "/srv/web"] {
+            app.search_query = query.to_string();
+            app.apply_search().unwrap();
+            let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+            assert_eq!(labels, ["web", "  └─ %4 0:"]);
+        }
+        app.search_query = "detached".to_string();
+        app.apply_search().unwrap();
+        assert!(app.rows.is_empty());
+
+        let line = highlight("needs review", &[16, 17, 18], 10);
+        let spans = line
+            .spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style.fg.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                ("needs ".to_string(), false),
+                ("rev".to_string(), true),
+                ("iew".to_string(), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn search_combines_field_filters_with_free_text() {
+        let tmux = fixture();
+        let store = ContextStore::temp();
+        store
+            .upsert_pane(
+                "api",
+                "%2",
+                Some(crate::context::AgentStatus::Waiting),
+                Some("flaky test".to_string()),
+            )
+            .unwrap();
+        store
+            .upsert_session(
+                "web".to_string(),
+                None,
+                Some(crate::context::AgentStatus::Waiting),
+                None,
+            )
+            .unwrap();
+        let mut app = App::load(&tmux, store).unwrap();
+
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "status:waiting");
+        let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+        assert_eq!(labels, ["api", "  └─ %2 0:", "web"]);
+
+        app.search_query = r#"ctx:"flaky test" !name:web"#.to_string();
+        app.apply_search().unwrap();
+        assert_eq!(app.rows.len(), 2);
+
+        app.search_query = "pane:3 !name:api".to_string();
+        app.apply_search().unwrap();
+        let labels = app.rows.iter().map(row_label).collect::<Vec<_>>();
+        assert_eq!(labels, ["web", "  └─ %3 0:"]);
+
+        // A half-typed filter keeps the last results and explains why.
+        app.search_query = "updated:<".to_string();
+        app.apply_search().unwrap();
+        assert_eq!(app.rows.len(), 2);
+        assert!(render(&mut app).contains("Invalid duration"));
+    }
+
+    #[test]
+    fn search_filters_sessions_with_fzf() {
+        if Command::new("fzf").arg("--version").output().is_err() {
+            return;
+        }
+        let tmux = fixture();
+        let mut app = App::load(&tmux, ContextStore::temp()).unwrap();
+        app.search_backend = SearchBackend::Fzf;
+
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "web");
+        assert_eq!(app.rows.len(), 1);
+        assert_eq!(selected_label(&app), "web");
+    }
 }
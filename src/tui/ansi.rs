@@ -0,0 +1,148 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+
+/// Converts `tmux capture-pane -e` output into styled text. Only SGR
+/// sequences are interpreted; other escape sequences are dropped.
+pub fn to_text(input: &str) -> Text<'static> {
+    let mut style = Style::default();
+    let lines = input
+        .lines()
+        .map(|line| parse_line(line, &mut style))
+        .collect::<Vec<_>>();
+    Text::from(lines)
+}
+
+fn parse_line(line: &str, style: &mut Style) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            current.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                let mut params = String::new();
+                let mut terminator = None;
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        terminator = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+                if terminator == Some('m') {
+                    if !current.is_empty() {
+                        spans.push(Span::styled(std::mem::take(&mut current), *style));
+                    }
+                    *style = apply_sgr(*style, &params);
+                }
+            }
+            Some(']') => {
+                // OSC sequences end with BEL or ST (ESC \).
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !current.is_empty() {
+        spans.push(Span::styled(current, *style));
+    }
+    Line::from(spans)
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes = params
+        .split(';')
+        .map(|code| code.parse::<u16>().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let mut index = 0;
+    while index < codes.len() {
+        let code = codes[index];
+        match code {
+            0 => style = Style::default(),
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            5 => style = style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style = style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style = style.remove_modifier(Modifier::ITALIC),
+            24 => style = style.remove_modifier(Modifier::UNDERLINED),
+            25 => style = style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style = style.remove_modifier(Modifier::REVERSED),
+            29 => style = style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style = style.fg(Color::Indexed((code - 30) as u8)),
+            39 => style.fg = None,
+            40..=47 => style = style.bg(Color::Indexed((code - 40) as u8)),
+            49 => style.bg = None,
+            90..=97 => style = style.fg(Color::Indexed((code - 90 + 8) as u8)),
+            100..=107 => style = style.bg(Color::Indexed((code - 100 + 8) as u8)),
+            38 | 48 => {
+                let (color, used) = extended_color(&codes[index + 1..]);
+                if let Some(color) = color {
+                    style = if code == 38 {
+                        style.fg(color)
+                    } else {
+                        style.bg(color)
+                    };
+                }
+                index += used;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    style
+}
+
+/// Parses the arguments after a 38/48 code, returning the color and how many
+/// codes it consumed.
+fn extended_color(codes: &[u16]) -> (Option<Color>, usize) {
+    let channel = |index: usize| codes.get(index).map(|value| (*value).min(255) as u8);
+    match codes.first() {
+        Some(5) => (channel(1).map(Color::Indexed), 2),
+        Some(2) => match (channel(1), channel(2), channel(3)) {
+            (Some(r), Some(g), Some(b)) => (Some(Color::Rgb(r, g, b)), 4),
+            _ => (None, codes.len()),
+        },
+        _ => (None, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_sgr_colors_and_drops_other_sequences() {
+        let text = to_text(
+            "\x1b[1;31merror\x1b[0m: \x1b]0;title\x07done\x1b[K\n\x1b[38;5;208mx\x1b[48;2;1;2;3my",
+        );
+        assert_eq!(text.lines.len(), 2);
+        let first = &text.lines[0].spans;
+        assert_eq!(first[0].content, "error");
+        assert_eq!(
+            first[0].style,
+            Style::default()
+                .fg(Color::Indexed(1))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(first[1].content, ": done");
+        assert_eq!(first[1].style, Style::default());
+        let second = &text.lines[1].spans;
+        assert_eq!(second[0].style.fg, Some(Color::Indexed(208)));
+        assert_eq!(second[1].style.bg, Some(Color::Rgb(1, 2, 3)));
+    }
+}
@@ -0,0 +1,162 @@
+//! The board view: agents as cards in one column per status.
+
+use super::{RowItem, RowKey, SessionRow, filter_status, status_style};
+use crate::context::AgentStatus;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+
+/// Column statuses, left to right. Failed agents are finished too and sit
+/// under `done`, drawn in red; sessions without a status sit under `none`.
+pub const COLUMNS: [AgentStatus; 5] = [
+    AgentStatus::Working,
+    AgentStatus::Waiting,
+    AgentStatus::Idle,
+    AgentStatus::Done,
+    AgentStatus::None,
+];
+
+/// Lines per card: title, context and a gap.
+const CARD_HEIGHT: u16 = 3;
+
+fn column_of(status: &AgentStatus) -> usize {
+    match status {
+        AgentStatus::Working => 0,
+        AgentStatus::Waiting => 1,
+        AgentStatus::Idle => 2,
+        AgentStatus::Done | AgentStatus::Failed => 3,
+        AgentStatus::None => 4,
+    }
+}
+
+#[derive(Clone)]
+pub struct Card {
+    pub item: RowItem,
+    title: String,
+    context: String,
+    status: AgentStatus,
+}
+
+/// Cards per column, in table order. As in the status line, each pane with
+/// a status is one agent, and a session is one only when none of its panes
+/// has a status, so every session listed in the table has a card.
+pub fn columns(sessions: &[SessionRow], infer: bool) -> [Vec<Card>; 5] {
+    let mut columns: [Vec<Card>; 5] = Default::default();
+    for session in sessions {
+        let mut pane_cards = 0;
+        for pane in &session.panes {
+            let Some(status) = filter_status(pane.status.as_ref(), pane.inferred.as_ref(), infer)
+            else {
+                continue;
+            };
+            if *status == AgentStatus::None {
+                continue;
+            }
+            pane_cards += 1;
+            columns[column_of(status)].push(Card {
+                item: RowItem::Pane(pane.clone()),
+                title: format!("{} {} {}", session.name, pane.id, pane.window),
+                context: pane.context.clone(),
+                status: status.clone(),
+            });
+        }
+        if pane_cards > 0 {
+            continue;
+        }
+        let status = filter_status(session.status.as_ref(), session.inferred.as_ref(), infer)
+            .unwrap_or(&AgentStatus::None);
+        columns[column_of(status)].push(Card {
+            item: RowItem::Session(session.clone()),
+            title: session.name.clone(),
+            context: session.context.clone(),
+            status: status.clone(),
+        });
+    }
+    columns
+}
+
+/// Which card is selected, by column and position.
+#[derive(Clone, Debug, Default)]
+pub struct Board {
+    pub column: usize,
+    pub index: usize,
+}
+
+impl Board {
+    pub fn selected<'c>(&self, columns: &'c [Vec<Card>; 5]) -> Option<&'c Card> {
+        columns[self.column].get(self.index)
+    }
+
+    /// Moves `step` columns left (negative) or right, keeping the row where
+    /// the new column has one.
+    pub fn step_column(&mut self, columns: &[Vec<Card>; 5], step: isize) {
+        self.column = self
+            .column
+            .saturating_add_signed(step)
+            .min(COLUMNS.len() - 1);
+        self.clamp(columns);
+    }
+
+    pub fn step_card(&mut self, columns: &[Vec<Card>; 5], step: isize) {
+        self.index = self.index.saturating_add_signed(step);
+        self.clamp(columns);
+    }
+
+    /// Selects the card for `key`, or keeps the position when it is gone.
+    pub fn select(&mut self, columns: &[Vec<Card>; 5], key: &RowKey) {
+        for (column, cards) in columns.iter().enumerate() {
+            if let Some(index) = cards.iter().position(|card| &card.item.key() == key) {
+                self.column = column;
+                self.index = index;
+                return;
+            }
+        }
+        self.clamp(columns);
+    }
+
+    fn clamp(&mut self, columns: &[Vec<Card>; 5]) {
+        self.index = self.index.min(columns[self.column].len().saturating_sub(1));
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, columns: &[Vec<Card>; 5]) {
+        let areas = Layout::horizontal([Constraint::Ratio(1, 5); 5]).split(area);
+        for (column, (status, cards)) in COLUMNS.iter().zip(columns).enumerate() {
+            let area = areas[column];
+            let selected = (column == self.column).then_some(self.index);
+            // Scroll just far enough to keep the selected card in view.
+            let visible = usize::from((area.height.saturating_sub(2) / CARD_HEIGHT).max(1));
+            let offset = selected.map_or(0, |index| (index + 1).saturating_sub(visible));
+            let mut lines = Vec::new();
+            for (index, card) in cards.iter().enumerate().skip(offset).take(visible) {
+                let mut title = Style::default().add_modifier(Modifier::BOLD);
+                if selected == Some(index) {
+                    title = title.add_modifier(Modifier::REVERSED);
+                }
+                lines.push(Line::from(Span::styled(card.title.clone(), title)));
+                let context = if card.status == AgentStatus::Failed {
+                    Span::styled(
+                        format!("failed · {}", card.context),
+                        status_style(Some(&AgentStatus::Failed)),
+                    )
+                } else {
+                    Span::styled(
+                        card.context.clone(),
+                        Style::default().add_modifier(Modifier::DIM),
+                    )
+                };
+                lines.push(Line::from(context));
+                lines.push(Line::default());
+            }
+            let mut block = Block::default().borders(Borders::ALL).title(Span::styled(
+                format!(" {status} ({}) ", cards.len()),
+                status_style(Some(status)),
+            ));
+            if selected.is_some() {
+                block = block.border_style(status_style(Some(status)));
+            }
+            frame.render_widget(Paragraph::new(lines).block(block), area);
+        }
+    }
+}
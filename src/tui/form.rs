@@ -0,0 +1,554 @@
+use crate::actions::NewSession;
+use crate::context::AgentStatus;
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use std::path::PathBuf;
+use unicode_width::UnicodeWidthStr;
+
+/// What the caller should do after a form handled a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormAction {
+    Continue,
+    Submit,
+    Cancel,
+}
+
+/// A single-line text field with a cursor.
+#[derive(Clone, Debug, Default)]
+pub struct TextInput {
+    value: String,
+    /// Char index the next character is inserted at.
+    cursor: usize,
+}
+
+impl TextInput {
+    /// Starts with the cursor after `value`.
+    pub fn new(value: impl Into<String>) -> Self {
+        let value = value.into();
+        Self {
+            cursor: value.chars().count(),
+            value,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Applies an editing or cursor key, returning whether it was one.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let len = self.value.chars().count();
+        match key.code {
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.value.remove(self.byte_index());
+            }
+            KeyCode::Delete if self.cursor < len => {
+                self.value.remove(self.byte_index());
+            }
+            KeyCode::Backspace | KeyCode::Delete => {}
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('a') if control => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            KeyCode::Char('e') if control => self.cursor = len,
+            // Like readline: everything before the cursor.
+            KeyCode::Char('u') if control => {
+                self.value.replace_range(..self.byte_index(), "");
+                self.cursor = 0;
+            }
+            KeyCode::Char(c) if !control => {
+                self.value.insert(self.byte_index(), c);
+                self.cursor += 1;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Inserts pasted text at the cursor, with line breaks and tabs turned
+    /// into spaces to keep it on one line.
+    pub fn paste(&mut self, text: &str) {
+        let text = text
+            .trim_end_matches(['\r', '\n'])
+            .replace("\r\n", " ")
+            .replace(['\r', '\n', '\t'], " ");
+        self.value.insert_str(self.byte_index(), &text);
+        self.cursor += text.chars().count();
+    }
+
+    /// Display width of the text before the cursor, for placing the
+    /// terminal cursor.
+    pub fn cursor_width(&self) -> u16 {
+        let before = &self.value[..self.byte_index()];
+        u16::try_from(UnicodeWidthStr::width(before)).unwrap_or(u16::MAX)
+    }
+
+    fn byte_index(&self) -> usize {
+        self.value
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.value.len(), |(index, _)| index)
+    }
+
+    /// The trimmed value, or `None` when only whitespace was entered.
+    fn optional(&self) -> Option<String> {
+        let value = self.value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Name,
+    Directory,
+    Command,
+    Status,
+    Context,
+}
+
+const FIELDS: [Field; 5] = [
+    Field::Name,
+    Field::Directory,
+    Field::Command,
+    Field::Status,
+    Field::Context,
+];
+
+impl Field {
+    fn label(self) -> &'static str {
+        match self {
+            Field::Name => "Name",
+            Field::Directory => "Directory",
+            Field::Command => "Command",
+            Field::Status => "Status",
+            Field::Context => "Context",
+        }
+    }
+}
+
+/// The `n` dialog collecting everything `actions::create_session` needs.
+#[derive(Clone, Debug, Default)]
+pub struct NewSessionForm {
+    name: TextInput,
+    directory: TextInput,
+    command: TextInput,
+    /// Index into `status_choices`; 0 leaves the status unset.
+    status: usize,
+    context: TextInput,
+    focus: usize,
+    pub error: Option<String>,
+}
+
+impl NewSessionForm {
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormAction {
+        match key.code {
+            KeyCode::Esc => return FormAction::Cancel,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return FormAction::Cancel;
+            }
+            KeyCode::Enter => return FormAction::Submit,
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % FIELDS.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                self.focus = (self.focus + FIELDS.len() - 1) % FIELDS.len();
+            }
+            _ => match FIELDS[self.focus] {
+                Field::Status => {
+                    let choices = status_choices().len();
+                    match key.code {
+                        KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => {
+                            self.status = (self.status + 1) % choices;
+                        }
+                        KeyCode::Left | KeyCode::Char('h') => {
+                            self.status = (self.status + choices - 1) % choices;
+                        }
+                        _ => {}
+                    }
+                }
+                field => {
+                    if self.input_mut(field).handle_key(key) {
+                        self.error = None;
+                    }
+                }
+            },
+        }
+        FormAction::Continue
+    }
+
+    /// Pastes into the focused text field.
+    pub fn paste(&mut self, text: &str) {
+        let field = FIELDS[self.focus];
+        if field != Field::Status {
+            self.input_mut(field).paste(text);
+            self.error = None;
+        }
+    }
+
+    pub fn request(&self) -> NewSession {
+        NewSession {
+            name: self.name.value().trim().to_string(),
+            cwd: self.directory.optional().map(|path| expand_home(&path)),
+            command: self.command.optional(),
+            status: status_choices()[self.status].1.clone(),
+            context: self.context.optional(),
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let label_width = FIELDS
+            .iter()
+            .map(|field| field.label().len())
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        let mut cursor = None;
+        for (index, field) in FIELDS.iter().enumerate() {
+            let focused = index == self.focus;
+            let label = format!("{:>label_width$}: ", field.label());
+            let (value, placeholder) = match field {
+                Field::Status => (status_choices()[self.status].0.clone(), false),
+                Field::Directory if self.directory.value().is_empty() => {
+                    ("(tmux default)".to_string(), true)
+                }
+                Field::Command if self.command.value().is_empty() => {
+                    ("(default shell)".to_string(), true)
+                }
+                field => (self.input(*field).value().to_string(), false),
+            };
+            let mut value_style = Style::default();
+            if placeholder {
+                value_style = value_style.add_modifier(Modifier::DIM);
+            }
+            let mut label_style = Style::default();
+            if focused {
+                label_style = label_style.add_modifier(Modifier::BOLD);
+                if *field == Field::Status {
+                    value_style = value_style.add_modifier(Modifier::REVERSED);
+                } else {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let offset = label.len() as u16 + self.input(*field).cursor_width();
+                    cursor = Some(Position::new(
+                        area.x + 1 + offset,
+                        area.y + 1 + index as u16,
+                    ));
+                }
+            }
+            lines.push(Line::from(vec![
+                Span::styled(label, label_style),
+                Span::styled(value, value_style),
+            ]));
+        }
+        lines.push(Line::default());
+        match &self.error {
+            Some(error) => lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red))),
+            None => lines.push(Line::styled(
+                "(Tab) next | (←/→) status | (Enter) create | (Esc) cancel",
+                Style::default().add_modifier(Modifier::DIM),
+            )),
+        }
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("New session"));
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+        if let Some(cursor) = cursor {
+            frame.set_cursor_position(cursor);
+        }
+    }
+
+    /// Rows the dialog needs, borders included.
+    pub fn height() -> u16 {
+        FIELDS.len() as u16 + 4
+    }
+
+    fn input(&self, field: Field) -> &TextInput {
+        match field {
+            Field::Name | Field::Status => &self.name,
+            Field::Directory => &self.directory,
+            Field::Command => &self.command,
+            Field::Context => &self.context,
+        }
+    }
+
+    fn input_mut(&mut self, field: Field) -> &mut TextInput {
+        match field {
+            Field::Name | Field::Status => &mut self.name,
+            Field::Directory => &mut self.directory,
+            Field::Command => &mut self.command,
+            Field::Context => &mut self.context,
+        }
+    }
+}
+
+fn status_choices() -> Vec<(String, Option<AgentStatus>)> {
+    let mut choices = vec![("(unset)".to_string(), None)];
+    choices.extend(super::pane_status_options());
+    choices
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix('~')
+        && (rest.is_empty() || rest.starts_with('/'))
+        && let Some(home) = std::env::var_os("HOME")
+    {
+        let mut expanded = PathBuf::from(home);
+        expanded.push(rest.trim_start_matches('/'));
+        return expanded;
+    }
+    PathBuf::from(path)
+}
+
+/// A yes/no question, answered with `y`/`Enter` or `n`/`Esc`.
+#[derive(Clone, Debug)]
+pub struct ConfirmDialog {
+    title: String,
+    lines: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl ConfirmDialog {
+    pub fn new(title: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            error: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormAction {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => FormAction::Submit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                FormAction::Cancel
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => FormAction::Cancel,
+            _ => FormAction::Continue,
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let mut lines = self
+            .lines
+            .iter()
+            .map(|line| Line::from(line.as_str()))
+            .collect::<Vec<_>>();
+        lines.push(Line::default());
+        match &self.error {
+            Some(error) => lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red))),
+            None => lines.push(Line::styled(
+                "(y/Enter) confirm | (n/Esc) cancel",
+                Style::default().add_modifier(Modifier::DIM),
+            )),
+        }
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(self.title.as_str()),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+
+    /// Rows the dialog needs, borders included.
+    pub fn height(&self) -> u16 {
+        u16::try_from(self.lines.len())
+            .unwrap_or(u16::MAX)
+            .saturating_add(4)
+    }
+}
+
+/// A dialog asking for a single line of text.
+#[derive(Clone, Debug)]
+pub struct PromptDialog {
+    title: String,
+    input: TextInput,
+    pub error: Option<String>,
+}
+
+impl PromptDialog {
+    pub fn new(title: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            input: TextInput::new(value),
+            error: None,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        self.input.value()
+    }
+
+    /// The trimmed value, or `None` when the field was left empty.
+    pub fn optional(&self) -> Option<String> {
+        self.input.optional()
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.input.paste(text);
+        self.error = None;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormAction {
+        match key.code {
+            KeyCode::Esc => FormAction::Cancel,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                FormAction::Cancel
+            }
+            KeyCode::Enter => FormAction::Submit,
+            _ => {
+                if self.input.handle_key(key) {
+                    self.error = None;
+                }
+                FormAction::Continue
+            }
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let hint = match &self.error {
+            Some(error) => Line::styled(error.clone(), Style::default().fg(Color::Red)),
+            None => Line::styled(
+                "(Enter) save | (Esc) cancel",
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+        };
+        let lines = vec![Line::from(self.input.value()), Line::default(), hint];
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(self.title.as_str()),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+        let offset = self.input.cursor_width();
+        frame.set_cursor_position(Position::new(area.x + 1 + offset, area.y + 1));
+    }
+
+    /// Rows the dialog needs, borders included.
+    pub fn height() -> u16 {
+        5
+    }
+}
+
+/// Picks a status for a session or pane, starting at its current one.
+#[derive(Clone, Debug)]
+pub struct StatusDialog {
+    title: String,
+    choices: Vec<(String, Option<AgentStatus>)>,
+    selected: usize,
+}
+
+impl StatusDialog {
+    pub fn new(title: impl Into<String>, current: Option<&AgentStatus>) -> Self {
+        let choices = super::pane_status_options();
+        let selected = choices
+            .iter()
+            .position(|(_, status)| status.as_ref() == current)
+            .unwrap_or(0);
+        Self {
+            title: title.into(),
+            choices,
+            selected,
+        }
+    }
+
+    pub fn status(&self) -> Option<AgentStatus> {
+        self.choices[self.selected].1.clone()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormAction {
+        let len = self.choices.len();
+        match key.code {
+            KeyCode::Esc => return FormAction::Cancel,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return FormAction::Cancel;
+            }
+            KeyCode::Enter => return FormAction::Submit,
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => {
+                self.selected = (self.selected + 1) % len;
+            }
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => {
+                self.selected = (self.selected + len - 1) % len;
+            }
+            _ => {}
+        }
+        FormAction::Continue
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let choices = self
+            .choices
+            .iter()
+            .enumerate()
+            .map(|(index, (label, status))| {
+                let mut style = super::status_style(status.as_ref());
+                if index == self.selected {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                Span::styled(format!(" {label} "), style)
+            })
+            .collect::<Vec<_>>();
+        let lines = vec![
+            Line::from(choices),
+            Line::default(),
+            Line::styled(
+                "(←/→) pick | (Enter) save | (Esc) cancel",
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+        ];
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(self.title.as_str()),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+
+    /// Rows the dialog needs, borders included.
+    pub fn height() -> u16 {
+        5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(input: &mut TextInput, code: KeyCode, modifiers: KeyModifiers) {
+        input.handle_key(KeyEvent::new(code, modifiers));
+    }
+
+    #[test]
+    fn text_input_edits_at_the_cursor() {
+        let mut input = TextInput::new("flaky tst");
+        press(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Char('e'), KeyModifiers::NONE);
+        assert_eq!(input.value(), "flaky test");
+
+        press(&mut input, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        press(&mut input, KeyCode::Delete, KeyModifiers::NONE);
+        input.paste("F\n");
+        assert_eq!(input.value(), "Flaky test");
+        assert_eq!(input.cursor_width(), 1);
+
+        press(&mut input, KeyCode::End, KeyModifiers::NONE);
+        input.paste(" in\r\nCI");
+        press(&mut input, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(input.value(), "Flaky test in C");
+
+        // Multi-byte characters move as one.
+        let mut input = TextInput::new("añb");
+        press(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(input.value(), "ab");
+        press(&mut input, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(input.value(), "b");
+    }
+}